@@ -0,0 +1,16 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Record the build time (seconds since the epoch) for the version query
+fn main() {
+    let built_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default();
+    println!("cargo:rustc-env=BUILD_TIMESTAMP={}", built_at);
+    println!("cargo:rerun-if-env-changed=GIT_HASH");
+    // Without rerun-if-changed lines for the sources, declaring any rerun trigger stops cargo
+    // from rerunning the script on source changes, leaving BUILD_TIMESTAMP stale
+    println!("cargo:rerun-if-changed=src");
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed=Cargo.toml");
+}
@@ -1,13 +1,139 @@
 #[macro_use]
 extern crate serde;
-use candid::{Decode, Encode, Principal};
+use candid::{Decode, Encode, Nat, Principal};
+use ic_cdk::api::management_canister::http_request::{
+    http_request, CanisterHttpRequestArgument, HttpMethod, HttpResponse, TransformArgs, TransformContext,
+};
 use ic_cdk::api::time;
 use ic_stable_structures::memory_manager::{MemoryId, MemoryManager, VirtualMemory};
+use ic_stable_structures::storable::Blob;
 use ic_stable_structures::{BoundedStorable, Cell, DefaultMemoryImpl, StableBTreeMap, Storable};
-use std::{borrow::Cow, cell::RefCell};
+use ic_cdk_timers::TimerId;
+use sha2::{Digest, Sha256};
+use std::{
+    borrow::Cow,
+    cell::RefCell,
+    collections::{BTreeMap, BTreeSet},
+    mem::discriminant,
+    time::Duration,
+};
 
 type Memory = VirtualMemory<DefaultMemoryImpl>;
 type IdCell = Cell<u64, Memory>;
+type PrincipalCell = Cell<Vec<u8>, Memory>;
+type ConfigCell<T> = Cell<T, Memory>;
+
+// Monetary amount in minor units (hundredths) of the employee's currency
+type Money = u64;
+const MINOR_UNITS_PER_MAJOR: u64 = 100;
+
+// Upper bound on the number of records returned by a single listing call
+const MAX_PAGE_SIZE: u64 = 100;
+
+// Upper bound on the number of employees added by a single bulk import
+const MAX_BULK_SIZE: usize = 100;
+
+// Upper bound on the number of employees returned by a search
+const MAX_SEARCH_RESULTS: usize = 50;
+
+// Decimal places allowed in wage_per_hour; wages are stored in hundredths, so at most 2
+const DEFAULT_WAGE_DECIMAL_PLACES: u32 = 2;
+const MAX_WAGE_DECIMAL_PLACES: u32 = 2;
+const WAGE_DECIMAL_EPSILON: f64 = 1e-6;
+
+// Largest hourly wage and monthly salary accepted, in major units
+const MAX_WAGE_PER_HOUR: f64 = 1_000_000.0;
+const MAX_MONTHLY_SALARY: f64 = 100_000_000.0;
+
+// Most employees the canister stores, unless the admin configures otherwise
+const DEFAULT_MAX_EMPLOYEES: u64 = 100_000;
+
+// Upper bound on the length of an employee name, in characters
+const MAX_NAME_LENGTH: usize = 128;
+
+// Upper bound on the length of a payroll rejection reason, in characters
+const MAX_REASON_LENGTH: usize = 256;
+
+// Plausible working ages accepted for employees
+const MIN_EMPLOYEE_AGE: u32 = 16;
+const MAX_EMPLOYEE_AGE: u32 = 100;
+
+// Days of the week attendance may be recorded on by default, 0 = Monday through 4 = Friday
+const DEFAULT_WORKWEEK: [u8; 5] = [0, 1, 2, 3, 4];
+
+// Upper age (inclusive) and label of each age_distribution bracket, youngest first
+const AGE_BRACKETS: [(u32, &str); 6] = [
+    (17, "Under 18"),
+    (25, "18-25"),
+    (35, "26-35"),
+    (45, "36-45"),
+    (60, "46-60"),
+    (u32::MAX, "60+"),
+];
+
+// Age at which employees retire, unless the admin configures otherwise
+const DEFAULT_RETIREMENT_AGE: u32 = 60;
+const MIN_RETIREMENT_AGE: u32 = 40;
+const MAX_RETIREMENT_AGE: u32 = 75;
+
+// All timestamps are nanoseconds since the Unix epoch, matching ic_cdk::api::time()
+const NANOS_PER_MINUTE: u64 = 60 * 1_000_000_000;
+const NANOS_PER_HOUR: u64 = 60 * NANOS_PER_MINUTE;
+const NANOS_PER_DAY: u64 = 24 * NANOS_PER_HOUR;
+
+// ISO 4217 currency codes wages can be denominated in
+const SUPPORTED_CURRENCIES: [&str; 9] = ["AUD", "CAD", "CHF", "EUR", "GBP", "IDR", "JPY", "SGD", "USD"];
+const DEFAULT_CURRENCY: &str = "USD";
+
+// Longest shift accepted unless the admin configures otherwise
+const DEFAULT_MAX_SHIFT_HOURS: f64 = 16.0;
+
+// Length of the standard working day, unless the admin configures otherwise.
+// Paid leave covers one standard day and hours beyond it are paid as overtime.
+const DEFAULT_STANDARD_DAILY_HOURS: f64 = 8.0;
+
+// Working days a monthly salary is spread over to get a salaried employee's daily wage
+const STANDARD_WORKING_DAYS_PER_MONTH: u64 = 22;
+
+// Overtime hours are paid at this percentage of the hourly wage
+const OVERTIME_MULTIPLIER_PERCENT: u64 = 150;
+
+// Size limits for SK file uploads, in bytes
+const MAX_SK_CHUNK_SIZE: u32 = 64 * 1024;
+const MAX_SK_FILE_SIZE: u32 = 2 * 1024 * 1024;
+
+// Headroom for the Candid encoding around raw file bytes
+const ENCODING_OVERHEAD: u32 = 1024;
+
+// ICP ledger canister that wages are paid from, and its smallest unit per ICP
+const ICP_LEDGER_CANISTER_ID: &str = "ryjl3-tyaaa-aaaaa-aaaba-cai";
+const E8S_PER_ICP: u64 = 100_000_000;
+const E8S_DECIMAL_PLACES: usize = 8;
+
+// Fee charged by the ledger's legacy transfer endpoint, which requires it explicitly
+const LEDGER_TRANSFER_FEE_E8S: u64 = 10_000;
+
+// Price API queried for the value of one ICP; {currency} is replaced by the wage currency
+const DEFAULT_PRICE_API_URL: &str = "https://api.coinbase.com/v2/prices/ICP-{currency}/spot";
+const HTTP_OUTCALL_CYCLES: u128 = 2_000_000_000;
+const MAX_PRICE_RESPONSE_BYTES: u64 = 2048;
+
+// Decimal places the transform keeps of a price API rate, so replicas agree on small fluctuations
+const RATE_DECIMAL_PLACES: usize = 8;
+
+// How long a fetched exchange rate may be used for payments
+const RATE_MAX_AGE_NANOS: u64 = 5 * 60 * 1_000_000_000;
+
+// Size of a stable memory page, in bytes
+const WASM_PAGE_SIZE: u64 = 64 * 1024;
+
+// Client request ids are remembered this long so retried record_attendance calls are not applied twice
+const MAX_CLIENT_REQUEST_ID_LENGTH: usize = 64;
+const CLIENT_REQUEST_TTL_NANOS: u64 = NANOS_PER_DAY;
+const CLIENT_REQUEST_PRUNE_INTERVAL_NANOS: u64 = NANOS_PER_HOUR;
+
+// Label of the subtree holding employee records in the certified hash tree
+const EMPLOYEES_TREE_LABEL: &[u8] = b"employees";
 
 // PDF file structure for SK
 #[derive(candid::CandidType, Clone, Serialize, Deserialize)]
@@ -19,13 +145,110 @@ struct PdfFile {
     updated_at: Option<u64>,
 }
 
+// Finalized SK file without its bytes, which are kept as chunks in SK_FILE_CHUNKS
+#[derive(candid::CandidType, Clone, Serialize, Deserialize)]
+struct SkFileInfo {
+    id: u64,
+    chunk_count: u32,
+    size: u64,
+    is_verified: bool,
+    created_at: u64,
+    updated_at: Option<u64>,
+}
+
+// Single piece of an SK file, staged during a chunked upload or part of a finalized file
+#[derive(candid::CandidType, Clone, Serialize, Deserialize)]
+struct SkChunk {
+    data: Vec<u8>,
+}
+
 // Structure for attendance data
 #[derive(candid::CandidType, Clone, Serialize, Deserialize)]
 struct Attendance {
     check_in: u64,
     check_out: u64,
     total_hours: f64,
-    daily_wage: f64,
+    regular_wage: Money,
+    overtime_wage: Money,
+    daily_wage: Money,
+    tax_amount: Money,
+    pension_amount: Money,
+    net_wage: Money,
+    late_by_minutes: u64,
+}
+
+// Scheduled start of the working day and how late a check-in may be without counting as late
+#[derive(candid::CandidType, Clone, Serialize, Deserialize, Default)]
+struct ShiftSchedule {
+    // Minutes after midnight UTC; None disables lateness tracking
+    start_minute: Option<u32>,
+    grace_minutes: u32,
+}
+
+// Employee-proposed change to a recorded shift, awaiting a manager
+#[derive(candid::CandidType, Clone, Serialize, Deserialize)]
+struct CorrectionRequest {
+    proposed_check_in: u64,
+    proposed_check_out: u64,
+    requested_by: String,
+    requested_at: u64,
+}
+
+// Kinds of leave; Sick and Vacation are paid a standard day, Unpaid earns nothing
+#[derive(candid::CandidType, Clone, Copy, Serialize, Deserialize, PartialEq)]
+enum LeaveType {
+    Sick,
+    Vacation,
+    Unpaid,
+}
+
+// Day of leave taken instead of a recorded shift
+#[derive(candid::CandidType, Clone, Serialize, Deserialize)]
+struct LeaveRecord {
+    leave_type: LeaveType,
+    daily_wage: Money,
+    recorded_at: u64,
+}
+
+// Fractions of the gross wage withheld for tax and pension contributions
+#[derive(candid::CandidType, Clone, Serialize, Deserialize, Default)]
+struct DeductionRates {
+    tax_rate: f64,
+    pension_rate: f64,
+}
+
+// How an employee is paid: by the hour worked, or a fixed monthly salary
+#[derive(candid::CandidType, Clone, Copy, Serialize, Deserialize, Default, PartialEq)]
+enum EmploymentType {
+    #[default]
+    Hourly,
+    Salaried,
+}
+
+// Addressing scheme of an employee's wallet
+#[derive(candid::CandidType, Clone, Copy, Serialize, Deserialize, Default, PartialEq)]
+enum WalletKind {
+    // Principal in its text form, paid through icrc1_transfer
+    #[default]
+    Principal,
+    // 32-byte ledger account identifier as 64 hex characters, paid through the legacy transfer
+    AccountIdentifier,
+}
+
+// How prorated wages are rounded to whole minor units
+#[derive(candid::CandidType, Clone, Copy, Serialize, Deserialize, Default, PartialEq)]
+enum RoundingMode {
+    #[default]
+    NearestCent,
+    Floor,
+    Ceil,
+}
+
+// Hourly wage floor and optional ceiling enforced on every employee
+#[derive(candid::CandidType, Clone, Serialize, Deserialize, Default)]
+struct WageBounds {
+    min_wage_per_hour: Money,
+    max_wage_per_hour: Option<Money>,
 }
 
 // Employee structure with personal and payroll data
@@ -35,30 +258,173 @@ struct Employee {
     name: String,
     age: u32,
     pension_age: u32,
-    wage_per_hour: f64,
-    sk_file: Option<PdfFile>,
+    years_to_pension: u32,
+    wage_per_hour: Money,
+    employment_type: EmploymentType,
+    monthly_salary: Money,
+    currency: String,
+    has_sk_file: bool,
     wallet_address: String,
+    wallet_kind: WalletKind,
+    is_active: bool,
     created_at: u64,
     updated_at: Option<u64>,
 }
 
+// Lightweight view of an employee for listings; SK file bytes are never included
+#[derive(candid::CandidType, Clone, Serialize, Deserialize)]
+struct EmployeeSummary {
+    nip: u64,
+    name: String,
+    age: u32,
+    wage_per_hour: Money,
+    wallet_address: String,
+    has_sk_file: bool,
+    is_verified: bool,
+}
+
 // Structure for payroll approval process
 #[derive(candid::CandidType, Clone, Serialize, Deserialize, Default)]
 struct PayrollApproval {
     employee_nip: u64,
     attendance_date: u64,
-    wage_amount: f64,
+    wage_amount: Money,
+    currency: String,
     status: ApprovalStatus,
     manager_wallet: String,
+    reason: Option<String>,
+    created_at: u64,
+    // Set by the first payment attempt so retries send the identical ledger transfer
+    payment: Option<PendingPayment>,
+}
+
+// Ledger transfer chosen for an approval; the ledger deduplicates resubmissions of it
+#[derive(candid::CandidType, Clone, Serialize, Deserialize)]
+struct PendingPayment {
+    amount_e8s: u64,
+    created_at_time: u64,
 }
 
 // Enum to define payroll approval statuses
-#[derive(candid::CandidType, Clone, Serialize, Deserialize, Default)]
+#[derive(candid::CandidType, Clone, Serialize, Deserialize, Default, PartialEq)]
 enum ApprovalStatus {
     #[default]
     Pending,
     Approved,
     Rejected,
+    Paid { block_index: u64 },
+}
+
+// Kinds of change published to the polling event feed
+#[derive(candid::CandidType, Clone, Serialize, Deserialize, PartialEq)]
+enum EventType {
+    ApprovalApproved,
+    ApprovalRejected,
+    WagePaid,
+    EmployeeAdded,
+}
+
+// Entry of the event feed; seq starts at 1 and increases by one per event
+#[derive(candid::CandidType, Clone, Serialize, Deserialize)]
+struct Event {
+    seq: u64,
+    event_type: EventType,
+    nip: u64,
+    timestamp: u64,
+}
+
+// Audit record of a single approval status transition.
+// old_status is None when the approval was created, new_status is None when it was removed.
+#[derive(candid::CandidType, Clone, Serialize, Deserialize)]
+struct ApprovalEvent {
+    employee_nip: u64,
+    attendance_date: u64,
+    old_status: Option<ApprovalStatus>,
+    new_status: Option<ApprovalStatus>,
+    actor: String,
+    timestamp: u64,
+}
+
+// Attendance record created by an earlier record_attendance call with the same client request id
+#[derive(candid::CandidType, Clone, Serialize, Deserialize)]
+struct ProcessedRequest {
+    nip: u64,
+    date: u64,
+    processed_at: u64,
+}
+
+// Employee record together with the proof that the subnet certified it.
+// The witness is a CBOR-encoded hash tree whose leaf for the NIP holds the employee's stored bytes.
+#[derive(candid::CandidType, Clone, Serialize, Deserialize)]
+struct CertifiedEmployee {
+    employee: Employee,
+    certificate: Vec<u8>,
+    witness: Vec<u8>,
+}
+
+// Payslip view of a single working day
+#[derive(candid::CandidType, Clone, Serialize, Deserialize)]
+struct Payslip {
+    employee_nip: u64,
+    employee_name: String,
+    date: u64,
+    attendance: Attendance,
+    approval_status: Option<ApprovalStatus>,
+    gross_wage: Money,
+    net_wage: Money,
+    currency: String,
+    is_paid: bool,
+}
+
+// Settings for the daily job that opens approval requests automatically
+#[derive(candid::CandidType, Clone, Serialize, Deserialize, Default)]
+struct AutoApprovalConfig {
+    enabled: bool,
+    manager_wallet: String,
+}
+
+// Value of one ICP in a fiat currency, as last fetched from the price API
+#[derive(candid::CandidType, Clone, Serialize, Deserialize)]
+struct ExchangeRate {
+    currency: String,
+    rate: f64,
+    fetched_at: u64,
+}
+
+// Latest exchange rate fetched for each currency
+#[derive(candid::CandidType, Clone, Serialize, Deserialize, Default)]
+struct ExchangeRateCache {
+    rates: Vec<ExchangeRate>,
+}
+
+// Headline numbers for the admin dashboard
+#[derive(candid::CandidType, Clone, Serialize, Deserialize, Default)]
+struct Statistics {
+    employee_count: u64,
+    pending_approvals: u64,
+    approved_approvals: u64,
+    rejected_approvals: u64,
+    paid_approvals: u64,
+    total_approved_wage_amount: Money,
+}
+
+// Resource usage and record counts for monitoring and cycle top-ups
+#[derive(candid::CandidType, Clone, Serialize, Deserialize)]
+struct CanisterStats {
+    cycle_balance: u128,
+    stable_memory_bytes: u64,
+    employee_count: u64,
+    attendance_count: u64,
+    approval_count: u64,
+}
+
+// Highest role the caller holds, for frontends deciding which controls to show
+#[derive(candid::CandidType, Clone, Copy, Serialize, Deserialize, PartialEq)]
+enum Role {
+    Admin,
+    Manager,
+    Employee,
+    Unknown,
 }
 
 // Error enum to handle various error types
@@ -67,11 +433,91 @@ enum Error {
     NotFound { msg: String },
     InvalidWallet { msg: String },
     InvalidInput { msg: String },
+    Unauthorized { msg: String },
+    PaymentFailed { msg: String },
+    RateUnavailable { msg: String },
 }
 
-// Implement storage capabilities for Attendance
-impl Storable for Attendance {
-    fn to_bytes(&self) -> Cow<[u8]> {
+// ICRC-1 account on the ICP ledger
+#[derive(candid::CandidType, Deserialize)]
+struct Account {
+    owner: Principal,
+    subaccount: Option<Vec<u8>>,
+}
+
+// Arguments for the ledger's icrc1_transfer endpoint
+#[derive(candid::CandidType, Deserialize)]
+struct TransferArg {
+    from_subaccount: Option<Vec<u8>>,
+    to: Account,
+    amount: Nat,
+    fee: Option<Nat>,
+    memo: Option<Vec<u8>>,
+    created_at_time: Option<u64>,
+}
+
+// Amount of ICP in the ledger's legacy interface
+#[derive(candid::CandidType, Deserialize, Debug)]
+struct Tokens {
+    e8s: u64,
+}
+
+// Timestamp in the ledger's legacy interface
+#[derive(candid::CandidType, Deserialize)]
+struct TimeStamp {
+    timestamp_nanos: u64,
+}
+
+// Arguments for the ledger's legacy transfer endpoint, which pays account identifiers
+#[derive(candid::CandidType, Deserialize)]
+struct LegacyTransferArgs {
+    memo: u64,
+    amount: Tokens,
+    fee: Tokens,
+    from_subaccount: Option<Vec<u8>>,
+    to: Vec<u8>,
+    created_at_time: Option<TimeStamp>,
+}
+
+// Errors returned by the ledger's legacy transfer endpoint
+#[derive(candid::CandidType, Deserialize, Debug)]
+enum LegacyTransferError {
+    BadFee { expected_fee: Tokens },
+    InsufficientFunds { balance: Tokens },
+    TxTooOld { allowed_window_nanos: u64 },
+    TxCreatedInFuture,
+    TxDuplicate { duplicate_of: u64 },
+}
+
+// Failed ledger transfer. Rejected means the ledger definitely did not execute it, so a new
+// transfer may replace it; Unknown covers failed calls and any outcome that might have executed.
+enum TransferFailure {
+    Rejected(Error),
+    Unknown(Error),
+}
+
+// Destination of a wage payment on the ICP ledger
+enum Recipient {
+    Principal(Principal),
+    AccountIdentifier(Vec<u8>),
+}
+
+// Errors returned by the ledger's icrc1_transfer endpoint
+#[derive(candid::CandidType, Deserialize, Debug)]
+enum TransferError {
+    BadFee { expected_fee: Nat },
+    BadBurn { min_burn_amount: Nat },
+    InsufficientFunds { balance: Nat },
+    TooOld,
+    CreatedInFuture { ledger_time: u64 },
+    TemporarilyUnavailable,
+    Duplicate { duplicate_of: Nat },
+    GenericError { error_code: Nat, message: String },
+}
+
+// Implement storage capabilities for DeductionRates
+impl Storable for DeductionRates {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
         Cow::Owned(Encode!(self).unwrap())
     }
 
@@ -80,14 +526,20 @@ impl Storable for Attendance {
     }
 }
 
-impl BoundedStorable for Attendance {
-    const MAX_SIZE: u32 = 2048;
-    const IS_FIXED_SIZE: bool = false;
+// Implement storage capabilities for WageBounds
+impl Storable for WageBounds {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).unwrap()
+    }
 }
 
-// Implement storage capabilities for Employee
-impl Storable for Employee {
-    fn to_bytes(&self) -> Cow<[u8]> {
+// Implement storage capabilities for ShiftSchedule
+impl Storable for ShiftSchedule {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
         Cow::Owned(Encode!(self).unwrap())
     }
 
@@ -96,14 +548,20 @@ impl Storable for Employee {
     }
 }
 
-impl BoundedStorable for Employee {
-    const MAX_SIZE: u32 = 2048;
-    const IS_FIXED_SIZE: bool = false;
+// Implement storage capabilities for RoundingMode
+impl Storable for RoundingMode {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).unwrap()
+    }
 }
 
-// Implement storage capabilities for PayrollApproval
-impl Storable for PayrollApproval {
-    fn to_bytes(&self) -> Cow<[u8]> {
+// Implement storage capabilities for AutoApprovalConfig
+impl Storable for AutoApprovalConfig {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
         Cow::Owned(Encode!(self).unwrap())
     }
 
@@ -112,197 +570,4369 @@ impl Storable for PayrollApproval {
     }
 }
 
-impl BoundedStorable for PayrollApproval {
-    const MAX_SIZE: u32 = 2048;
+// Implement storage capabilities for ExchangeRateCache
+impl Storable for ExchangeRateCache {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).unwrap()
+    }
+}
+
+// Implement storage capabilities for SkFileInfo
+impl Storable for SkFileInfo {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).unwrap()
+    }
+}
+
+impl BoundedStorable for SkFileInfo {
+    const MAX_SIZE: u32 = 256;
     const IS_FIXED_SIZE: bool = false;
 }
 
-// Thread-local storage setup for memory and data persistence
-thread_local! {
-    static MEMORY_MANAGER: RefCell<MemoryManager<DefaultMemoryImpl>> = RefCell::new(
-        MemoryManager::init(DefaultMemoryImpl::default())
-    );
+// Implement storage capabilities for SkChunk
+impl Storable for SkChunk {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).unwrap())
+    }
 
-    static ID_COUNTER: RefCell<IdCell> = RefCell::new(
-        IdCell::init(MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(0))), 0)
-            .expect("Cannot create a counter")
-    );
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).unwrap()
+    }
+}
 
-    static EMPLOYEE_STORAGE: RefCell<StableBTreeMap<u64, Employee, Memory>> = RefCell::new(
-        StableBTreeMap::init(MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(1))))
-    );
+impl BoundedStorable for SkChunk {
+    const MAX_SIZE: u32 = MAX_SK_CHUNK_SIZE + ENCODING_OVERHEAD;
+    const IS_FIXED_SIZE: bool = false;
+}
 
-    static ATTENDANCE_STORAGE: RefCell<StableBTreeMap<(u64, u64), Attendance, Memory>> = RefCell::new(
-        StableBTreeMap::init(MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(2))))
-    );
+// Attendance as written by the first release, with the wage in major units
+#[derive(candid::CandidType, Deserialize)]
+struct LegacyAttendance {
+    check_in: u64,
+    check_out: u64,
+    total_hours: f64,
+    daily_wage: f64,
+}
 
-    static APPROVAL_STORAGE: RefCell<StableBTreeMap<(u64, u64), PayrollApproval, Memory>> = RefCell::new(
-        StableBTreeMap::init(MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(3))))
-    );
+impl From<LegacyAttendance> for Attendance {
+    fn from(legacy: LegacyAttendance) -> Self {
+        let daily_wage = to_minor_units(legacy.daily_wage);
+        Attendance {
+            check_in: legacy.check_in,
+            check_out: legacy.check_out,
+            total_hours: legacy.total_hours,
+            regular_wage: daily_wage,
+            overtime_wage: 0,
+            daily_wage,
+            tax_amount: 0,
+            pension_amount: 0,
+            net_wage: daily_wage,
+            late_by_minutes: 0,
+        }
+    }
 }
 
-// Payload structure for adding new employees
-#[derive(candid::CandidType, Serialize, Deserialize)]
-struct EmployeePayload {
+// Employee as written by the first release, with the wage in major units.
+// Its sk_file field could never be set, so it is ignored and has_sk_file starts out false.
+#[derive(candid::CandidType, Deserialize)]
+struct LegacyEmployee {
+    nip: u64,
     name: String,
     age: u32,
+    pension_age: u32,
     wage_per_hour: f64,
     wallet_address: String,
+    created_at: u64,
+    updated_at: Option<u64>,
 }
 
-// Calculate pension age based on current age
-fn calculate_pension_age(age: u32) -> u32 {
-    60 - age
+impl From<LegacyEmployee> for Employee {
+    fn from(legacy: LegacyEmployee) -> Self {
+        Employee {
+            nip: legacy.nip,
+            name: legacy.name,
+            age: legacy.age,
+            pension_age: legacy.pension_age,
+            years_to_pension: legacy.pension_age.saturating_sub(legacy.age),
+            wage_per_hour: to_minor_units(legacy.wage_per_hour),
+            employment_type: EmploymentType::Hourly,
+            monthly_salary: 0,
+            currency: DEFAULT_CURRENCY.to_string(),
+            has_sk_file: false,
+            wallet_address: legacy.wallet_address,
+            wallet_kind: WalletKind::Principal,
+            is_active: true,
+            created_at: legacy.created_at,
+            updated_at: legacy.updated_at,
+        }
+    }
 }
 
-// Calculate total working hours from check-in and check-out times
-fn calculate_work_hours(check_in: u64, check_out: u64) -> f64 {
-    if check_out <= check_in {
-        return 0.0; // Invalid check-out time
-    }
-    let diff = check_out - check_in;
-    (diff as f64) / (1000.0 * 60.0 * 60.0)
+// Payroll approval as written by the first release, with the amount in major units
+#[derive(candid::CandidType, Deserialize)]
+struct LegacyPayrollApproval {
+    employee_nip: u64,
+    attendance_date: u64,
+    wage_amount: f64,
+    status: ApprovalStatus,
+    manager_wallet: String,
 }
 
-// Calculate daily wage based on total hours worked and hourly wage
-fn calculate_daily_wage(total_hours: f64, wage_per_hour: f64) -> f64 {
-    total_hours * wage_per_hour
+impl From<LegacyPayrollApproval> for PayrollApproval {
+    fn from(legacy: LegacyPayrollApproval) -> Self {
+        PayrollApproval {
+            employee_nip: legacy.employee_nip,
+            attendance_date: legacy.attendance_date,
+            wage_amount: to_minor_units(legacy.wage_amount),
+            currency: DEFAULT_CURRENCY.to_string(),
+            status: legacy.status,
+            manager_wallet: legacy.manager_wallet,
+            reason: None,
+            created_at: 0,
+            payment: None,
+        }
+    }
 }
 
-// Add a new employee to the storage
-#[ic_cdk::update]
-fn add_employee(payload: EmployeePayload) -> Result<Employee, Error> {
-    // Input validation
-    if payload.name.trim().is_empty() || payload.age == 0 || payload.wage_per_hour <= 0.0 {
-        return Err(Error::InvalidInput {
-            msg: "Invalid employee data".to_string(),
-        });
+// Implement storage capabilities for Attendance
+impl Storable for Attendance {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).unwrap())
     }
 
-    let nip = ID_COUNTER
-        .with(|counter| {
-            let current_value = *counter.borrow().get();
-            counter.borrow_mut().set(current_value + 1)
-        })
-        .expect("Cannot increment ID counter");
+    // Records written by the first release, with f64 amounts, still decode
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self)
+            .or_else(|_| Decode!(bytes.as_ref(), LegacyAttendance).map(Self::from))
+            .unwrap()
+    }
+}
 
-    let pension_age = calculate_pension_age(payload.age);
+impl BoundedStorable for Attendance {
+    const MAX_SIZE: u32 = 2048;
+    const IS_FIXED_SIZE: bool = false;
+}
 
-    let employee = Employee {
-        nip,
-        name: payload.name,
-        age: payload.age,
-        pension_age,
-        wage_per_hour: payload.wage_per_hour,
-        sk_file: None,
-        wallet_address: payload.wallet_address,
+// Implement storage capabilities for CorrectionRequest
+impl Storable for CorrectionRequest {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).unwrap()
+    }
+}
+
+impl BoundedStorable for CorrectionRequest {
+    const MAX_SIZE: u32 = 256;
+    const IS_FIXED_SIZE: bool = false;
+}
+
+// Implement storage capabilities for LeaveRecord
+impl Storable for LeaveRecord {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).unwrap()
+    }
+}
+
+impl BoundedStorable for LeaveRecord {
+    const MAX_SIZE: u32 = 256;
+    const IS_FIXED_SIZE: bool = false;
+}
+
+// Implement storage capabilities for Employee
+impl Storable for Employee {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).unwrap())
+    }
+
+    // Records written by the first release, with f64 amounts, still decode
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self)
+            .or_else(|_| Decode!(bytes.as_ref(), LegacyEmployee).map(Self::from))
+            .unwrap()
+    }
+}
+
+impl BoundedStorable for Employee {
+    const MAX_SIZE: u32 = 2048;
+    const IS_FIXED_SIZE: bool = false;
+}
+
+// Implement storage capabilities for PayrollApproval
+impl Storable for PayrollApproval {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).unwrap())
+    }
+
+    // Records written by the first release, with f64 amounts, still decode
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self)
+            .or_else(|_| Decode!(bytes.as_ref(), LegacyPayrollApproval).map(Self::from))
+            .unwrap()
+    }
+}
+
+impl BoundedStorable for PayrollApproval {
+    const MAX_SIZE: u32 = 2048;
+    const IS_FIXED_SIZE: bool = false;
+}
+
+// Implement storage capabilities for ApprovalEvent
+impl Storable for ApprovalEvent {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).unwrap()
+    }
+}
+
+impl BoundedStorable for ApprovalEvent {
+    const MAX_SIZE: u32 = 2048;
+    const IS_FIXED_SIZE: bool = false;
+}
+
+// Implement storage capabilities for Event
+impl Storable for Event {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).unwrap()
+    }
+}
+
+impl BoundedStorable for Event {
+    const MAX_SIZE: u32 = 256;
+    const IS_FIXED_SIZE: bool = false;
+}
+
+// Implement storage capabilities for ProcessedRequest
+impl Storable for ProcessedRequest {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(Encode!(self).unwrap())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).unwrap()
+    }
+}
+
+impl BoundedStorable for ProcessedRequest {
+    const MAX_SIZE: u32 = 256;
+    const IS_FIXED_SIZE: bool = false;
+}
+
+// Thread-local storage setup for memory and data persistence
+thread_local! {
+    static MEMORY_MANAGER: RefCell<MemoryManager<DefaultMemoryImpl>> = RefCell::new(
+        MemoryManager::init(DefaultMemoryImpl::default())
+    );
+
+    static ID_COUNTER: RefCell<IdCell> = RefCell::new(
+        IdCell::init(MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(0))), 0)
+            .expect("Cannot create a counter")
+    );
+
+    static EMPLOYEE_STORAGE: RefCell<StableBTreeMap<u64, Employee, Memory>> = RefCell::new(
+        StableBTreeMap::init(MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(1))))
+    );
+
+    static ATTENDANCE_STORAGE: RefCell<StableBTreeMap<(u64, u64), Attendance, Memory>> = RefCell::new(
+        StableBTreeMap::init(MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(2))))
+    );
+
+    static APPROVAL_STORAGE: RefCell<StableBTreeMap<(u64, u64), PayrollApproval, Memory>> = RefCell::new(
+        StableBTreeMap::init(MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(3))))
+    );
+
+    static SK_CHUNK_STORAGE: RefCell<StableBTreeMap<(u64, u32), SkChunk, Memory>> = RefCell::new(
+        StableBTreeMap::init(MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(4))))
+    );
+
+    // SK file metadata; the bytes live in SK_FILE_CHUNKS so map nodes stay small
+    static SK_FILE_STORAGE: RefCell<StableBTreeMap<u64, SkFileInfo, Memory>> = RefCell::new(
+        StableBTreeMap::init(MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(5))))
+    );
+
+    static ADMIN: RefCell<PrincipalCell> = RefCell::new(
+        PrincipalCell::init(MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(6))), Vec::new())
+            .expect("Cannot create the admin cell")
+    );
+
+    static RETIREMENT_AGE: RefCell<ConfigCell<u32>> = RefCell::new(
+        ConfigCell::init(MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(7))), DEFAULT_RETIREMENT_AGE)
+            .expect("Cannot create the retirement age cell")
+    );
+
+    static APPROVAL_LOG: RefCell<StableBTreeMap<u64, ApprovalEvent, Memory>> = RefCell::new(
+        StableBTreeMap::init(MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(8))))
+    );
+
+    static DEDUCTION_RATES: RefCell<ConfigCell<DeductionRates>> = RefCell::new(
+        ConfigCell::init(MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(9))), DeductionRates::default())
+            .expect("Cannot create the deduction rates cell")
+    );
+
+    static MAX_SHIFT_HOURS: RefCell<ConfigCell<f64>> = RefCell::new(
+        ConfigCell::init(MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(10))), DEFAULT_MAX_SHIFT_HOURS)
+            .expect("Cannot create the max shift hours cell")
+    );
+
+    static DEFAULT_EMPLOYEE_CURRENCY: RefCell<ConfigCell<String>> = RefCell::new(
+        ConfigCell::init(MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(11))), DEFAULT_CURRENCY.to_string())
+            .expect("Cannot create the default currency cell")
+    );
+
+    static AUTO_APPROVAL_CONFIG: RefCell<ConfigCell<AutoApprovalConfig>> = RefCell::new(
+        ConfigCell::init(MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(12))), AutoApprovalConfig::default())
+            .expect("Cannot create the auto approval config cell")
+    );
+
+    static PRICE_API_URL: RefCell<ConfigCell<String>> = RefCell::new(
+        ConfigCell::init(MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(13))), DEFAULT_PRICE_API_URL.to_string())
+            .expect("Cannot create the price API URL cell")
+    );
+
+    static EXCHANGE_RATES: RefCell<ConfigCell<ExchangeRateCache>> = RefCell::new(
+        ConfigCell::init(MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(14))), ExchangeRateCache::default())
+            .expect("Cannot create the exchange rate cell")
+    );
+
+    // Hourly wage in effect from each timestamp onward, keyed by (nip, effective_at)
+    static WAGE_HISTORY: RefCell<StableBTreeMap<(u64, u64), Money, Memory>> = RefCell::new(
+        StableBTreeMap::init(MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(15))))
+    );
+
+    // Client request ids already handled by record_attendance
+    static PROCESSED_REQUESTS: RefCell<StableBTreeMap<Blob<MAX_CLIENT_REQUEST_ID_LENGTH>, ProcessedRequest, Memory>> = RefCell::new(
+        StableBTreeMap::init(MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(16))))
+    );
+
+    // Manager assigned by request_approval_default; empty until the admin sets one
+    static DEFAULT_MANAGER: RefCell<PrincipalCell> = RefCell::new(
+        PrincipalCell::init(MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(17))), Vec::new())
+            .expect("Cannot create the default manager cell")
+    );
+
+    // Managers allowed to approve any payroll, keyed by principal bytes
+    static MANAGERS: RefCell<StableBTreeMap<Blob<29>, (), Memory>> = RefCell::new(
+        StableBTreeMap::init(MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(18))))
+    );
+
+    // Append-only feed of changes for clients to poll
+    static EVENT_LOG: RefCell<StableBTreeMap<u64, Event, Memory>> = RefCell::new(
+        StableBTreeMap::init(MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(19))))
+    );
+
+    static WAGE_BOUNDS: RefCell<ConfigCell<WageBounds>> = RefCell::new(
+        ConfigCell::init(MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(20))), WageBounds::default())
+            .expect("Cannot create the wage bounds cell")
+    );
+
+    static WORKWEEK: RefCell<ConfigCell<Vec<u8>>> = RefCell::new(
+        ConfigCell::init(MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(21))), DEFAULT_WORKWEEK.to_vec())
+            .expect("Cannot create the workweek cell")
+    );
+
+    static ROUNDING_MODE: RefCell<ConfigCell<RoundingMode>> = RefCell::new(
+        ConfigCell::init(MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(22))), RoundingMode::default())
+            .expect("Cannot create the rounding mode cell")
+    );
+
+    static LEAVE_STORAGE: RefCell<StableBTreeMap<(u64, u64), LeaveRecord, Memory>> = RefCell::new(
+        StableBTreeMap::init(MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(23))))
+    );
+
+    static SHIFT_SCHEDULE: RefCell<ConfigCell<ShiftSchedule>> = RefCell::new(
+        ConfigCell::init(MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(24))), ShiftSchedule::default())
+            .expect("Cannot create the shift schedule cell")
+    );
+
+    static WAGE_DECIMAL_PLACES: RefCell<ConfigCell<u32>> = RefCell::new(
+        ConfigCell::init(MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(25))), DEFAULT_WAGE_DECIMAL_PLACES)
+            .expect("Cannot create the wage decimal places cell")
+    );
+
+    static STANDARD_DAILY_HOURS: RefCell<ConfigCell<f64>> = RefCell::new(
+        ConfigCell::init(MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(27))), DEFAULT_STANDARD_DAILY_HOURS)
+            .expect("Cannot create the standard daily hours cell")
+    );
+
+    static MAX_EMPLOYEES: RefCell<ConfigCell<u64>> = RefCell::new(
+        ConfigCell::init(MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(28))), DEFAULT_MAX_EMPLOYEES)
+            .expect("Cannot create the max employees cell")
+    );
+
+    static CORRECTION_STORAGE: RefCell<StableBTreeMap<(u64, u64), CorrectionRequest, Memory>> = RefCell::new(
+        StableBTreeMap::init(MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(26))))
+    );
+
+    // Bytes of finalized SK files, keyed by (nip, chunk_index)
+    static SK_FILE_CHUNKS: RefCell<StableBTreeMap<(u64, u32), SkChunk, Memory>> = RefCell::new(
+        StableBTreeMap::init(MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(29))))
+    );
+
+    // Timers live on the heap, so post_upgrade re-arms this from AUTO_APPROVAL_CONFIG
+    static AUTO_APPROVAL_TIMER: RefCell<Option<TimerId>> = const { RefCell::new(None) };
+
+    // Approvals whose ledger transfer is awaiting a response
+    static PAYMENTS_IN_FLIGHT: RefCell<BTreeSet<(u64, u64)>> = const { RefCell::new(BTreeSet::new()) };
+
+    // Hashes of the certified employee tree; heap only, so init and post_upgrade rebuild it
+    static CERTIFIED_TREE: RefCell<EmployeeTree> = const {
+        RefCell::new(EmployeeTree { leaves: BTreeMap::new(), forks: BTreeMap::new() })
+    };
+}
+
+// Set the admin principal allowed to manage employees
+#[ic_cdk::init]
+fn init(admin: Principal) {
+    ADMIN
+        .with(|cell| cell.borrow_mut().set(admin.as_slice().to_vec()))
+        .expect("Cannot set the admin");
+    certify_employees();
+    schedule_request_id_pruning();
+}
+
+// Nothing to save before an upgrade: all state already lives in stable memory
+#[ic_cdk::pre_upgrade]
+fn pre_upgrade() {}
+
+// Re-open every stable structure against its MemoryId right after an upgrade, so a
+// memory layout that no longer matches traps the upgrade instead of a later call.
+// Canisters installed before the admin existed have none yet; the optional argument sets it.
+#[ic_cdk::post_upgrade]
+fn post_upgrade(admin: Option<Principal>) {
+    ID_COUNTER.with(|_| ());
+    EMPLOYEE_STORAGE.with(|_| ());
+    ATTENDANCE_STORAGE.with(|_| ());
+    APPROVAL_STORAGE.with(|_| ());
+    SK_CHUNK_STORAGE.with(|_| ());
+    SK_FILE_STORAGE.with(|_| ());
+    ADMIN.with(|_| ());
+    RETIREMENT_AGE.with(|_| ());
+    APPROVAL_LOG.with(|_| ());
+    DEDUCTION_RATES.with(|_| ());
+    MAX_SHIFT_HOURS.with(|_| ());
+    DEFAULT_EMPLOYEE_CURRENCY.with(|_| ());
+    AUTO_APPROVAL_CONFIG.with(|_| ());
+    PRICE_API_URL.with(|_| ());
+    EXCHANGE_RATES.with(|_| ());
+    WAGE_HISTORY.with(|_| ());
+    PROCESSED_REQUESTS.with(|_| ());
+    DEFAULT_MANAGER.with(|_| ());
+    MANAGERS.with(|_| ());
+    EVENT_LOG.with(|_| ());
+    WAGE_BOUNDS.with(|_| ());
+    WORKWEEK.with(|_| ());
+    ROUNDING_MODE.with(|_| ());
+    LEAVE_STORAGE.with(|_| ());
+    SHIFT_SCHEDULE.with(|_| ());
+    WAGE_DECIMAL_PLACES.with(|_| ());
+    CORRECTION_STORAGE.with(|_| ());
+    STANDARD_DAILY_HOURS.with(|_| ());
+    MAX_EMPLOYEES.with(|_| ());
+    SK_FILE_CHUNKS.with(|_| ());
+
+    if let Some(admin) = admin {
+        if ADMIN.with(|cell| cell.borrow().get().is_empty()) {
+            ADMIN
+                .with(|cell| cell.borrow_mut().set(admin.as_slice().to_vec()))
+                .expect("Cannot set the admin");
+        }
+    }
+
+    // Neither certified data nor the heap tree behind it survives an upgrade
+    certify_employees();
+
+    schedule_request_id_pruning();
+
+    if get_auto_approval_config().enabled {
+        schedule_auto_approval();
+    }
+}
+
+// Payload structure for adding new employees; wage_per_hour is in major units (e.g. 12.50)
+#[derive(candid::CandidType, Serialize, Deserialize)]
+struct EmployeePayload {
+    name: String,
+    age: u32,
+    wage_per_hour: f64,
+    wallet_address: String,
+    currency: Option<String>,
+    // Hourly when adding if omitted; an update keeps the current value
+    employment_type: Option<EmploymentType>,
+    // Major units, required for salaried employees; an update keeps the current value if omitted
+    monthly_salary: Option<f64>,
+}
+
+// Calculate the age at which an employee retires
+fn calculate_pension_age() -> u32 {
+    get_retirement_age()
+}
+
+// Calculate the years left until retirement, zero for anyone already past it
+fn years_until_pension(age: u32) -> u32 {
+    calculate_pension_age().saturating_sub(age)
+}
+
+// Calculate total working hours from check-in and check-out times in nanoseconds
+fn calculate_work_hours(check_in: u64, check_out: u64) -> f64 {
+    if check_out <= check_in {
+        return 0.0; // Invalid check-out time
+    }
+    let diff = check_out - check_in;
+    (diff as f64) / (NANOS_PER_HOUR as f64)
+}
+
+// Convert a major-unit amount received at the API boundary into minor units
+fn to_minor_units(amount: f64) -> Money {
+    (amount * MINOR_UNITS_PER_MAJOR as f64).round() as Money
+}
+
+// Convert a minor-unit amount into major units
+fn to_major_units(amount: Money) -> f64 {
+    amount as f64 / MINOR_UNITS_PER_MAJOR as f64
+}
+
+// Pay a duration in nanoseconds at a percentage of the hourly wage, rounded per the configured mode
+fn prorate_wage(worked_nanos: u64, wage_per_hour: Money, percent: u64) -> Money {
+    let numerator = u128::from(worked_nanos) * u128::from(wage_per_hour) * u128::from(percent);
+    let denominator = u128::from(NANOS_PER_HOUR) * 100;
+    divide_rounded(numerator, denominator) as Money
+}
+
+// Divide into whole minor units, rounding per the configured mode
+fn divide_rounded(numerator: u128, denominator: u128) -> u128 {
+    match get_rounding_mode() {
+        RoundingMode::Floor => numerator / denominator,
+        RoundingMode::NearestCent => (numerator + denominator / 2) / denominator,
+        RoundingMode::Ceil => numerator.div_ceil(denominator),
+    }
+}
+
+// Daily share of a monthly salary
+fn salaried_daily_wage(monthly_salary: Money) -> Money {
+    divide_rounded(u128::from(monthly_salary), u128::from(STANDARD_WORKING_DAYS_PER_MONTH)) as Money
+}
+
+// Length of the configured standard working day, in nanoseconds
+fn standard_day_nanos() -> u64 {
+    (get_standard_daily_hours() * NANOS_PER_HOUR as f64) as u64
+}
+
+// Calculate daily wage based on time worked (in nanoseconds) and hourly wage
+fn calculate_daily_wage(worked_nanos: u64, wage_per_hour: Money) -> Money {
+    prorate_wage(worked_nanos, wage_per_hour, 100)
+}
+
+// Calculate daily wage paying time beyond the threshold at the overtime multiplier
+fn calculate_daily_wage_with_overtime(
+    worked_nanos: u64,
+    wage_per_hour: Money,
+    overtime_threshold_nanos: u64,
+    multiplier_percent: u64,
+) -> Money {
+    let regular_nanos = worked_nanos.min(overtime_threshold_nanos);
+    let overtime_nanos = worked_nanos.saturating_sub(overtime_threshold_nanos);
+    calculate_daily_wage(regular_nanos, wage_per_hour) + prorate_wage(overtime_nanos, wage_per_hour, multiplier_percent)
+}
+
+// Validate the fields shared by add_employee and update_employee
+fn validate_employee_payload(payload: &EmployeePayload, current: Option<&Employee>) -> Result<(), Error> {
+    if payload.name.trim().is_empty() {
+        return Err(Error::InvalidInput {
+            msg: "Invalid employee data".to_string(),
+        });
+    }
+    validate_amount("wage_per_hour", payload.wage_per_hour, MAX_WAGE_PER_HOUR)?;
+    if let Some(monthly_salary) = payload.monthly_salary {
+        validate_amount("monthly_salary", monthly_salary, MAX_MONTHLY_SALARY)?;
+    }
+    match resolve_pay_model(payload, current) {
+        (EmploymentType::Hourly, _) => {
+            if to_minor_units(payload.wage_per_hour) == 0 {
+                return Err(Error::InvalidInput {
+                    msg: "Invalid employee data".to_string(),
+                });
+            }
+            validate_wage_bounds(to_minor_units(payload.wage_per_hour))?;
+        }
+        (EmploymentType::Salaried, monthly_salary) => {
+            if monthly_salary == 0 {
+                return Err(Error::InvalidInput {
+                    msg: "Salaried employees must have a positive monthly_salary".to_string(),
+                });
+            }
+        }
+    }
+    if payload.age < MIN_EMPLOYEE_AGE {
+        return Err(Error::InvalidInput {
+            msg: format!("Age {} is below the minimum working age of {}", payload.age, MIN_EMPLOYEE_AGE),
+        });
+    }
+    if payload.age > MAX_EMPLOYEE_AGE {
+        return Err(Error::InvalidInput {
+            msg: format!("Age {} is above the maximum of {}", payload.age, MAX_EMPLOYEE_AGE),
+        });
+    }
+    validate_name(&payload.name)?;
+    validate_wage_precision(payload.wage_per_hour)?;
+    validate_payout_wallet(&payload.wallet_address)?;
+    if let Some(currency) = &payload.currency {
+        validate_currency(currency)?;
+    }
+    Ok(())
+}
+
+// Ensure a major-unit amount is a finite number between zero and the given maximum
+fn validate_amount(field: &str, amount: f64, max: f64) -> Result<(), Error> {
+    if !amount.is_finite() || !(0.0..=max).contains(&amount) {
+        return Err(Error::InvalidInput {
+            msg: format!("{} must be a number between 0 and {}", field, max),
+        });
+    }
+    Ok(())
+}
+
+// Work out the employment type and monthly salary a payload leaves the employee with
+fn resolve_pay_model(payload: &EmployeePayload, current: Option<&Employee>) -> (EmploymentType, Money) {
+    let employment_type = payload
+        .employment_type
+        .or(current.map(|employee| employee.employment_type))
+        .unwrap_or_default();
+    let monthly_salary = payload
+        .monthly_salary
+        .map(to_minor_units)
+        .or(current.map(|employee| employee.monthly_salary))
+        .unwrap_or(0);
+    (employment_type, monthly_salary)
+}
+
+// Check that a major-unit wage has no more decimal places than configured
+fn validate_wage_precision(wage_per_hour: f64) -> Result<(), Error> {
+    let places = get_wage_decimal_places();
+    let scaled = wage_per_hour * 10f64.powi(places as i32);
+    if (scaled - scaled.round()).abs() > WAGE_DECIMAL_EPSILON {
+        return Err(Error::InvalidInput {
+            msg: format!("Wage per hour can have at most {} decimal places", places),
+        });
+    }
+    Ok(())
+}
+
+// Check an hourly wage against the configured floor and ceiling
+fn validate_wage_bounds(wage_per_hour: Money) -> Result<(), Error> {
+    let bounds = get_wage_bounds();
+    let too_high = match bounds.max_wage_per_hour {
+        Some(max) => wage_per_hour > max,
+        None => false,
+    };
+    if wage_per_hour < bounds.min_wage_per_hour || too_high {
+        let max = match bounds.max_wage_per_hour {
+            Some(max) => format!("{:.2}", to_major_units(max)),
+            None => "no maximum".to_string(),
+        };
+        return Err(Error::InvalidInput {
+            msg: format!(
+                "Wage per hour must be between {:.2} and {}",
+                to_major_units(bounds.min_wage_per_hour),
+                max
+            ),
+        });
+    }
+    Ok(())
+}
+
+// Validate an employee name, returning it with surrounding whitespace trimmed
+fn validate_name(name: &str) -> Result<String, Error> {
+    let name = name.trim();
+    if name.chars().count() > MAX_NAME_LENGTH {
+        return Err(Error::InvalidInput {
+            msg: format!("Name cannot be longer than {} characters", MAX_NAME_LENGTH),
+        });
+    }
+    if name.chars().any(char::is_control) {
+        return Err(Error::InvalidInput {
+            msg: "Name cannot contain control characters".to_string(),
+        });
+    }
+    Ok(name.to_string())
+}
+
+// Validate a currency code against the supported set, returning it normalized
+fn validate_currency(currency: &str) -> Result<String, Error> {
+    let currency = currency.trim().to_uppercase();
+    if !SUPPORTED_CURRENCIES.contains(&currency.as_str()) {
+        return Err(Error::InvalidInput {
+            msg: format!("Unsupported currency {}", currency),
+        });
+    }
+    Ok(currency)
+}
+
+// Calculate a fraction of an amount, rounded to the nearest minor unit
+fn apply_rate(amount: Money, rate: f64) -> Money {
+    (amount as f64 * rate).round() as Money
+}
+
+// Calculate the wage left after tax and pension deductions
+fn calculate_net_wage(gross: Money, tax_rate: f64, pension_rate: f64) -> Money {
+    gross
+        .saturating_sub(apply_rate(gross, tax_rate))
+        .saturating_sub(apply_rate(gross, pension_rate))
+}
+
+// Day key (whole days since the Unix epoch, UTC) of a timestamp in nanoseconds
+fn day_key(ts_nanos: u64) -> u64 {
+    ts_nanos / NANOS_PER_DAY
+}
+
+// Day key of the canister's current time, i.e. the date attendance is recorded under today
+#[ic_cdk::query]
+fn current_day_key() -> u64 {
+    day_key(time())
+}
+
+// Day of the week of a day key, 0 = Monday through 6 = Sunday; the epoch was a Thursday
+fn day_of_week(day: u64) -> u8 {
+    ((day + 3) % 7) as u8
+}
+
+// Convert a day key (days since the Unix epoch) into a (year, month, day) civil date
+fn day_key_to_ymd(day: u64) -> (u32, u32, u32) {
+    // Shift the epoch to 0000-03-01 so leap days fall at the end of each 400-year era
+    let z = day + 719_468;
+    let era = z / 146_097;
+    let doe = z % 146_097;
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = doy - (153 * mp + 2) / 5 + 1;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 };
+    let y = yoe + era * 400 + u64::from(m <= 2);
+    (y as u32, m as u32, d as u32)
+}
+
+// Ensure no other employee is already paid to the given wallet
+fn ensure_wallet_unused(wallet_address: &str, exclude_nip: Option<u64>) -> Result<(), Error> {
+    let wallet = validate_payout_wallet(wallet_address)?;
+    let in_use = EMPLOYEE_STORAGE.with(|storage| {
+        storage.borrow().iter().any(|(nip, employee)| {
+            Some(nip) != exclude_nip && validate_payout_wallet(&employee.wallet_address).ok().as_ref() == Some(&wallet)
+        })
+    });
+
+    if in_use {
+        return Err(Error::InvalidInput {
+            msg: "Wallet address is already used by another employee".to_string(),
+        });
+    }
+    Ok(())
+}
+
+// Add a new employee to the storage
+#[ic_cdk::update]
+fn add_employee(payload: EmployeePayload) -> Result<Employee, Error> {
+    ensure_admin()?;
+    insert_employee(payload)
+}
+
+// Add several employees at once, reporting the outcome of each payload separately
+#[ic_cdk::update]
+fn add_employees_bulk(payloads: Vec<EmployeePayload>) -> Result<Vec<Result<Employee, Error>>, Error> {
+    ensure_admin()?;
+    if payloads.len() > MAX_BULK_SIZE {
+        return Err(Error::InvalidInput {
+            msg: format!("At most {} employees can be added at once", MAX_BULK_SIZE),
+        });
+    }
+    Ok(payloads.into_iter().map(insert_employee).collect())
+}
+
+// Validate and store a new employee under the next NIP
+fn insert_employee(payload: EmployeePayload) -> Result<Employee, Error> {
+    ensure_employee_capacity(1)?;
+
+    // Input validation
+    validate_employee_payload(&payload, None)?;
+    ensure_wallet_unused(&payload.wallet_address, None)?;
+
+    let (wallet_kind, wallet_address) = validate_payout_wallet(&payload.wallet_address)?;
+    let (employment_type, monthly_salary) = resolve_pay_model(&payload, None);
+    let nip = ID_COUNTER.with(|counter| *counter.borrow().get());
+    let employee = Employee {
+        nip,
+        name: validate_name(&payload.name)?,
+        age: payload.age,
+        pension_age: calculate_pension_age(),
+        years_to_pension: years_until_pension(payload.age),
+        wage_per_hour: to_minor_units(payload.wage_per_hour),
+        employment_type,
+        monthly_salary,
+        currency: match &payload.currency {
+            Some(currency) => validate_currency(currency)?,
+            None => get_default_currency(),
+        },
+        has_sk_file: false,
+        wallet_address,
+        wallet_kind,
+        is_active: true,
+        created_at: time(),
+        updated_at: None,
+    };
+    ensure_fits_storage(&employee)?;
+    let next_nip = next_nip_after(nip)?;
+
+    ID_COUNTER
+        .with(|counter| counter.borrow_mut().set(next_nip))
+        .expect("Cannot increment ID counter");
+    EMPLOYEE_STORAGE.with(|storage| storage.borrow_mut().insert(nip, employee.clone()));
+    certify_employee(nip);
+    record_wage_change(nip, employee.created_at, employee.wage_per_hour);
+    publish_event(EventType::EmployeeAdded, nip);
+    Ok(employee)
+}
+
+// Ensure storing `additional` more employees keeps the canister within the employee limit
+fn ensure_employee_capacity(additional: u64) -> Result<(), Error> {
+    let stored = EMPLOYEE_STORAGE.with(|storage| storage.borrow().len());
+    if stored.saturating_add(additional) > get_max_employees() {
+        return Err(Error::InvalidInput {
+            msg: "employee limit reached".to_string(),
+        });
+    }
+    Ok(())
+}
+
+// Check that a record encodes within its map's MAX_SIZE, which the map would otherwise trap on
+fn ensure_fits_storage<T: BoundedStorable>(value: &T) -> Result<(), Error> {
+    if value.to_bytes().len() > T::MAX_SIZE as usize {
+        return Err(Error::InvalidInput {
+            msg: "record too large".to_string(),
+        });
+    }
+    Ok(())
+}
+
+// The NIP counter value that follows a stored NIP; u64::MAX would leave no next NIP
+fn next_nip_after(nip: u64) -> Result<u64, Error> {
+    nip.checked_add(1).ok_or_else(|| Error::InvalidInput {
+        msg: format!("NIP {} leaves no room for the next NIP", nip),
+    })
+}
+
+// Record the hourly wage an employee earns from `effective_at` onward
+fn record_wage_change(nip: u64, effective_at: u64, wage_per_hour: Money) {
+    WAGE_HISTORY.with(|history| history.borrow_mut().insert((nip, effective_at), wage_per_hour));
+}
+
+// Find the hourly wage that applied at the given timestamp.
+// Falls back to the current wage when no history predates it.
+fn wage_in_effect(employee: &Employee, at: u64) -> Money {
+    WAGE_HISTORY.with(|history| {
+        history
+            .borrow()
+            .range((employee.nip, 0)..=(employee.nip, at))
+            .last()
+            .map(|(_, wage)| wage)
+            .unwrap_or(employee.wage_per_hour)
+    })
+}
+
+// Retrieve every wage change for an employee as (effective_at, wage_per_hour), oldest first
+#[ic_cdk::query]
+fn get_wage_history(nip: u64) -> Vec<(u64, Money)> {
+    WAGE_HISTORY.with(|history| {
+        history
+            .borrow()
+            .range((nip, 0)..=(nip, u64::MAX))
+            .map(|((_, effective_at), wage)| (effective_at, wage))
+            .collect()
+    })
+}
+
+// Retrieve employee details with a certificate and witness clients can verify.
+// Certificates are only issued for query calls, so this fails when called as an update.
+#[ic_cdk::query]
+fn get_employee_certified(nip: u64) -> Result<CertifiedEmployee, Error> {
+    let employee = get_employee(nip)?;
+    let certificate = ic_cdk::api::data_certificate().ok_or_else(|| Error::InvalidInput {
+        msg: "Certificates are only available in query calls".to_string(),
+    })?;
+
+    let bytes = employee.to_bytes();
+    let witness = CERTIFIED_TREE.with(|tree| {
+        let tree = tree.borrow();
+        let root = tree.top(64, 0).expect("Stored employee is missing from the certified tree");
+
+        // CBOR self-describe tag, then labeled("employees", subtree)
+        let mut witness = vec![0xd9, 0xd9, 0xf7];
+        cbor_head(&mut witness, 4, 3);
+        cbor_head(&mut witness, 0, 2);
+        cbor_bytes(&mut witness, EMPLOYEES_TREE_LABEL);
+        tree.write_witness(&mut witness, root, nip, &bytes);
+        witness
+    });
+
+    Ok(CertifiedEmployee {
+        employee,
+        certificate,
+        witness,
+    })
+}
+
+// Rebuild the employee tree from storage and publish its root hash as the canister's certified data
+fn certify_employees() {
+    let leaves = EMPLOYEE_STORAGE.with(|storage| {
+        storage
+            .borrow()
+            .iter()
+            .map(|(nip, employee)| (nip, labeled_leaf_hash(nip, &employee.to_bytes())))
+            .collect()
+    });
+    let root = CERTIFIED_TREE.with(|tree| {
+        let mut tree = tree.borrow_mut();
+        *tree = EmployeeTree {
+            leaves,
+            forks: BTreeMap::new(),
+        };
+        tree.rehash(64, 0);
+        tree.root_hash()
+    });
+    publish_employees_root(&root);
+}
+
+// Re-hash the path to one employee after it was stored or removed, and publish the new root
+fn certify_employee(nip: u64) {
+    let bytes = EMPLOYEE_STORAGE.with(|storage| storage.borrow().get(&nip)).map(|employee| employee.to_bytes().into_owned());
+    let root = CERTIFIED_TREE.with(|tree| {
+        let mut tree = tree.borrow_mut();
+        tree.update(nip, bytes.as_deref());
+        tree.root_hash()
+    });
+    publish_employees_root(&root);
+}
+
+fn publish_employees_root(subtree_root: &[u8; 32]) {
+    let root = tree_hash("ic-hashtree-labeled", &[EMPLOYEES_TREE_LABEL, subtree_root]);
+    ic_cdk::api::set_certified_data(&root);
+}
+
+// Binary trie over the bits of each NIP, most significant first, with single-child nodes
+// collapsed into their child. A node is named by (level, nip >> level); level 0 holds the
+// labeled leaves and level 64 is the root. Only forks and leaves store a hash.
+struct EmployeeTree {
+    leaves: BTreeMap<u64, [u8; 32]>,
+    // Nodes with employees under both children
+    forks: BTreeMap<(u32, u64), [u8; 32]>,
+}
+
+impl EmployeeTree {
+    // The node a subtree collapses to: its only leaf or its topmost fork, None when empty
+    fn top(&self, level: u32, prefix: u64) -> Option<(u32, u64)> {
+        let low = (prefix as u128) << level;
+        let high = low + (1u128 << level) - 1;
+        let mut range = self.leaves.range(low as u64..=high as u64);
+        let first = *range.next()?.0;
+        let last = range.next_back().map_or(first, |(nip, _)| *nip);
+        let fork_level = u64::BITS - (first ^ last).leading_zeros();
+        Some((fork_level, ((first as u128) >> fork_level) as u64))
+    }
+
+    fn node_hash(&self, (level, prefix): (u32, u64)) -> [u8; 32] {
+        if level == 0 {
+            self.leaves[&prefix]
+        } else {
+            self.forks[&(level, prefix)]
+        }
+    }
+
+    fn subtree_hash(&self, level: u32, prefix: u64) -> Option<[u8; 32]> {
+        self.top(level, prefix).map(|node| self.node_hash(node))
+    }
+
+    fn root_hash(&self) -> [u8; 32] {
+        self.subtree_hash(64, 0).unwrap_or_else(|| tree_hash("ic-hashtree-empty", &[]))
+    }
+
+    // Store or remove one leaf and re-hash the forks on its path, bottom up
+    fn update(&mut self, nip: u64, bytes: Option<&[u8]>) {
+        match bytes {
+            Some(bytes) => self.leaves.insert(nip, labeled_leaf_hash(nip, bytes)),
+            None => self.leaves.remove(&nip),
+        };
+        for level in 1..=u64::BITS {
+            let prefix = ((nip as u128) >> level) as u64;
+            let left = self.subtree_hash(level - 1, prefix << 1);
+            let right = self.subtree_hash(level - 1, (prefix << 1) | 1);
+            match (left, right) {
+                (Some(left), Some(right)) => {
+                    self.forks.insert((level, prefix), tree_hash("ic-hashtree-fork", &[&left, &right]));
+                }
+                _ => {
+                    self.forks.remove(&(level, prefix));
+                }
+            }
+        }
+    }
+
+    // Hash every fork under a node from its leaves, returning the node's hash
+    fn rehash(&mut self, level: u32, prefix: u64) -> Option<[u8; 32]> {
+        let (level, prefix) = self.top(level, prefix)?;
+        if level == 0 {
+            return Some(self.leaves[&prefix]);
+        }
+        let left = self.rehash(level - 1, prefix << 1)?;
+        let right = self.rehash(level - 1, (prefix << 1) | 1)?;
+        let hash = tree_hash("ic-hashtree-fork", &[&left, &right]);
+        self.forks.insert((level, prefix), hash);
+        Some(hash)
+    }
+
+    // Encode the subtree under a node with every branch pruned except the path to nip
+    fn write_witness(&self, out: &mut Vec<u8>, (level, prefix): (u32, u64), nip: u64, bytes: &[u8]) {
+        if level == 0 {
+            cbor_head(out, 4, 3);
+            cbor_head(out, 0, 2);
+            cbor_bytes(out, &nip.to_be_bytes());
+            cbor_head(out, 4, 2);
+            cbor_head(out, 0, 3);
+            cbor_bytes(out, bytes);
+            return;
+        }
+
+        let left = self.top(level - 1, prefix << 1).expect("Fork without a left child");
+        let right = self.top(level - 1, (prefix << 1) | 1).expect("Fork without a right child");
+        cbor_head(out, 4, 3);
+        cbor_head(out, 0, 1);
+        if (nip >> (level - 1)) & 1 == 0 {
+            self.write_witness(out, left, nip, bytes);
+            write_pruned(out, &self.node_hash(right));
+        } else {
+            write_pruned(out, &self.node_hash(left));
+            self.write_witness(out, right, nip, bytes);
+        }
+    }
+}
+
+// Hash a hash tree node: SHA-256 over the length-prefixed domain separator and the node's parts
+fn tree_hash(domain: &str, parts: &[&[u8]]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update([domain.len() as u8]);
+    hasher.update(domain.as_bytes());
+    for part in parts {
+        hasher.update(part);
+    }
+    hasher.finalize().into()
+}
+
+// Hash of labeled(nip, leaf(bytes)); big-endian labels keep the tree sorted by NIP
+fn labeled_leaf_hash(nip: u64, bytes: &[u8]) -> [u8; 32] {
+    let leaf = tree_hash("ic-hashtree-leaf", &[bytes]);
+    tree_hash("ic-hashtree-labeled", &[&nip.to_be_bytes(), &leaf])
+}
+
+// Encode a pruned hash tree node
+fn write_pruned(out: &mut Vec<u8>, hash: &[u8; 32]) {
+    cbor_head(out, 4, 2);
+    cbor_head(out, 0, 4);
+    cbor_bytes(out, hash);
+}
+
+// Encode a CBOR byte string
+fn cbor_bytes(out: &mut Vec<u8>, bytes: &[u8]) {
+    cbor_head(out, 2, bytes.len() as u64);
+    out.extend_from_slice(bytes);
+}
+
+// Encode a CBOR item header with the given major type and argument
+fn cbor_head(out: &mut Vec<u8>, major: u8, value: u64) {
+    let major = major << 5;
+    if value < 24 {
+        out.push(major | value as u8);
+    } else if value <= u8::MAX as u64 {
+        out.extend_from_slice(&[major | 24, value as u8]);
+    } else if value <= u16::MAX as u64 {
+        out.push(major | 25);
+        out.extend_from_slice(&(value as u16).to_be_bytes());
+    } else if value <= u32::MAX as u64 {
+        out.push(major | 26);
+        out.extend_from_slice(&(value as u32).to_be_bytes());
+    } else {
+        out.push(major | 27);
+        out.extend_from_slice(&value.to_be_bytes());
+    }
+}
+
+// Retrieve the NIP the next add_employee call will assign.
+// add_employee hands out the counter's current value before incrementing it.
+#[ic_cdk::query]
+fn get_next_nip() -> u64 {
+    ID_COUNTER.with(|counter| *counter.borrow().get())
+}
+
+// Retrieve employee details by NIP
+#[ic_cdk::query]
+fn get_employee(nip: u64) -> Result<Employee, Error> {
+    EMPLOYEE_STORAGE.with(|storage| {
+        storage.borrow().get(&nip).ok_or(Error::NotFound {
+            msg: format!("Employee with NIP={} not found", nip),
+        })
+    })
+}
+
+// Find the employee who owns a wallet address
+#[ic_cdk::query]
+fn get_employee_by_wallet(wallet_address: String) -> Result<Employee, Error> {
+    let wallet = validate_payout_wallet(&wallet_address)?;
+
+    EMPLOYEE_STORAGE.with(|storage| {
+        storage
+            .borrow()
+            .iter()
+            .map(|(_, employee)| employee)
+            .find(|employee| validate_payout_wallet(&employee.wallet_address).ok().as_ref() == Some(&wallet))
+            .ok_or(Error::NotFound {
+                msg: format!("Employee with wallet {} not found", wallet_address),
+            })
+    })
+}
+
+// Count the whole days since the employee was added
+#[ic_cdk::query]
+fn employee_tenure_days(nip: u64) -> Result<u64, Error> {
+    let employee = get_employee(nip)?;
+    Ok(time().saturating_sub(employee.created_at) / NANOS_PER_DAY)
+}
+
+// Retrieve the canonical text form of an employee's wallet principal or account identifier
+#[ic_cdk::query]
+fn resolve_wallet(nip: u64) -> Result<String, Error> {
+    let employee = get_employee(nip)?;
+    validate_payout_wallet(&employee.wallet_address).map(|(_, wallet)| wallet)
+}
+
+// Check whether an employee has reached retirement age
+#[ic_cdk::query]
+fn is_eligible_for_pension(nip: u64) -> Result<bool, Error> {
+    let employee = get_employee(nip)?;
+    Ok(employee.age >= calculate_pension_age())
+}
+
+// List active employees retiring within the given number of years, soonest first.
+// Employees already past retirement age count as zero years away.
+#[ic_cdk::query]
+fn get_employees_near_retirement(within_years: u32) -> Vec<Employee> {
+    let mut employees: Vec<(u32, Employee)> = EMPLOYEE_STORAGE.with(|storage| {
+        storage
+            .borrow()
+            .iter()
+            .filter(|(_, employee)| employee.is_active)
+            .map(|(_, employee)| (years_until_pension(employee.age), employee))
+            .filter(|(years, _)| *years <= within_years)
+            .collect()
+    });
+    employees.sort_by_key(|(years, _)| *years);
+    employees.into_iter().map(|(_, employee)| employee).collect()
+}
+
+// Count active employees per age bracket, youngest bracket first
+#[ic_cdk::query]
+fn age_distribution() -> Vec<(String, u64)> {
+    let mut counts = [0u64; AGE_BRACKETS.len()];
+    EMPLOYEE_STORAGE.with(|storage| {
+        for (_, employee) in storage.borrow().iter().filter(|(_, employee)| employee.is_active) {
+            if let Some(bracket) = AGE_BRACKETS.iter().position(|(max_age, _)| employee.age <= *max_age) {
+                counts[bracket] += 1;
+            }
+        }
+    });
+    AGE_BRACKETS
+        .iter()
+        .zip(counts)
+        .map(|((_, label), count)| (label.to_string(), count))
+        .collect()
+}
+
+// List active employees whose hourly wage lies in [min, max], lowest wage first.
+// Bounds are in major units, like EmployeePayload.wage_per_hour.
+#[ic_cdk::query]
+fn employees_by_wage_range(min: f64, max: f64) -> Result<Vec<Employee>, Error> {
+    validate_amount("min", min, MAX_WAGE_PER_HOUR)?;
+    validate_amount("max", max, MAX_WAGE_PER_HOUR)?;
+    let (min, max) = (to_minor_units(min), to_minor_units(max));
+    if min > max {
+        return Err(Error::InvalidInput {
+            msg: "min must not be greater than max".to_string(),
+        });
+    }
+
+    let mut employees: Vec<Employee> = EMPLOYEE_STORAGE.with(|storage| {
+        storage
+            .borrow()
+            .iter()
+            .map(|(_, employee)| employee)
+            .filter(|employee| employee.is_active && (min..=max).contains(&employee.wage_per_hour))
+            .collect()
+    });
+    employees.sort_by_key(|employee| employee.wage_per_hour);
+    Ok(employees)
+}
+
+// List employees in NIP order, one page at a time; archived employees only on request
+#[ic_cdk::query]
+fn list_employees(offset: u64, limit: u64, include_archived: bool) -> Vec<Employee> {
+    let limit = limit.min(MAX_PAGE_SIZE) as usize;
+    EMPLOYEE_STORAGE.with(|storage| {
+        storage
+            .borrow()
+            .iter()
+            .map(|(_, employee)| employee)
+            .filter(|employee| include_archived || employee.is_active)
+            .skip(usize::try_from(offset).unwrap_or(usize::MAX))
+            .take(limit)
+            .collect()
+    })
+}
+
+// Page through active employees as summaries, ordered by NIP.
+// Use get_sk_file to fetch an employee's SK document when it is needed.
+#[ic_cdk::query]
+fn list_employee_summaries(offset: u64, limit: u64) -> Vec<EmployeeSummary> {
+    list_employees(offset, limit, false)
+        .into_iter()
+        .map(|employee| {
+            let is_verified = employee.has_sk_file
+                && SK_FILE_STORAGE.with(|storage| storage.borrow().get(&employee.nip).map(|sk_file| sk_file.is_verified))
+                    == Some(true);
+            EmployeeSummary {
+                nip: employee.nip,
+                name: employee.name,
+                age: employee.age,
+                wage_per_hour: employee.wage_per_hour,
+                wallet_address: employee.wallet_address,
+                has_sk_file: employee.has_sk_file,
+                is_verified,
+            }
+        })
+        .collect()
+}
+
+// Dump every employee, archived ones included, for an off-chain backup.
+// Large workforces may exceed the query instruction limit; use export_employees_page then.
+#[ic_cdk::query]
+fn export_all_employees() -> Result<Vec<Employee>, Error> {
+    ensure_admin()?;
+    Ok(EMPLOYEE_STORAGE.with(|storage| storage.borrow().iter().map(|(_, employee)| employee).collect()))
+}
+
+// Dump employees with NIP >= start_nip in NIP order, archived ones included.
+// Continue from the last returned NIP + 1 until an empty page comes back.
+#[ic_cdk::query]
+fn export_employees_page(start_nip: u64, limit: u64) -> Result<Vec<Employee>, Error> {
+    ensure_admin()?;
+    let limit = limit.min(MAX_PAGE_SIZE) as usize;
+    Ok(EMPLOYEE_STORAGE.with(|storage| {
+        storage
+            .borrow()
+            .range(start_nip..)
+            .take(limit)
+            .map(|(_, employee)| employee)
+            .collect()
+    }))
+}
+
+// Restore employees from a backup under their original NIPs, returning how many were imported.
+// Every record is checked against the rules add_employee applies before any is written; NIPs
+// already in use are refused, and the NIP counter moves past the highest NIP.
+#[ic_cdk::update]
+fn import_employees(employees: Vec<Employee>) -> Result<u64, Error> {
+    ensure_admin()?;
+    ensure_employee_capacity(employees.len() as u64)?;
+
+    let mut nips = BTreeSet::new();
+    let mut wallets = BTreeSet::new();
+    let employees = employees
+        .into_iter()
+        .map(|employee| {
+            let employee = validate_imported_employee(employee)?;
+            if !nips.insert(employee.nip) || EMPLOYEE_STORAGE.with(|storage| storage.borrow().contains_key(&employee.nip)) {
+                return Err(Error::InvalidInput {
+                    msg: format!("Employee with NIP={} already exists", employee.nip),
+                });
+            }
+            if !wallets.insert(employee.wallet_address.clone()) {
+                return Err(Error::InvalidInput {
+                    msg: "Wallet address is already used by another employee".to_string(),
+                });
+            }
+            Ok(employee)
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+
+    let count = employees.len() as u64;
+    for employee in employees {
+        let nip = employee.nip;
+        if get_wage_history(nip).is_empty() {
+            record_wage_change(nip, employee.created_at, employee.wage_per_hour);
+        }
+        EMPLOYEE_STORAGE.with(|storage| storage.borrow_mut().insert(nip, employee));
+        certify_employee(nip);
+        ID_COUNTER.with(|counter| {
+            let next_nip = next_nip_after(nip).ok().expect("Imported NIP was validated");
+            if *counter.borrow().get() < next_nip {
+                counter.borrow_mut().set(next_nip).expect("Cannot increment ID counter");
+            }
+        });
+    }
+
+    Ok(count)
+}
+
+// Check an imported employee with the rules add_employee applies and normalize it the same way.
+// Pension fields follow the current retirement age and has_sk_file follows the stored SK files.
+fn validate_imported_employee(mut employee: Employee) -> Result<Employee, Error> {
+    let payload = EmployeePayload {
+        name: employee.name.clone(),
+        age: employee.age,
+        wage_per_hour: to_major_units(employee.wage_per_hour),
+        wallet_address: employee.wallet_address.clone(),
+        currency: Some(employee.currency.clone()),
+        employment_type: Some(employee.employment_type),
+        monthly_salary: Some(to_major_units(employee.monthly_salary)),
+    };
+    validate_employee_payload(&payload, None)?;
+    ensure_wallet_unused(&employee.wallet_address, None)?;
+    next_nip_after(employee.nip)?;
+
+    let (wallet_kind, wallet_address) = validate_payout_wallet(&employee.wallet_address)?;
+    employee.name = validate_name(&employee.name)?;
+    employee.currency = validate_currency(&employee.currency)?;
+    employee.wallet_address = wallet_address;
+    employee.wallet_kind = wallet_kind;
+    employee.pension_age = calculate_pension_age();
+    employee.years_to_pension = years_until_pension(employee.age);
+    employee.has_sk_file = SK_FILE_STORAGE.with(|storage| storage.borrow().contains_key(&employee.nip));
+    ensure_fits_storage(&employee)?;
+    Ok(employee)
+}
+
+// Find employees whose name contains the query, ignoring case; archived employees only on request
+#[ic_cdk::query]
+fn search_employees_by_name(query: String, include_archived: bool) -> Vec<Employee> {
+    let query = query.trim().to_lowercase();
+    if query.is_empty() {
+        return Vec::new();
+    }
+
+    EMPLOYEE_STORAGE.with(|storage| {
+        storage
+            .borrow()
+            .iter()
+            .map(|(_, employee)| employee)
+            .filter(|employee| include_archived || employee.is_active)
+            .filter(|employee| employee.name.to_lowercase().contains(&query))
+            .take(MAX_SEARCH_RESULTS)
+            .collect()
+    })
+}
+
+// Archive an employee, keeping their records but removing them from active operations
+#[ic_cdk::update]
+fn archive_employee(nip: u64) -> Result<Employee, Error> {
+    set_employee_active(nip, false)
+}
+
+// Return an archived employee to active operations
+#[ic_cdk::update]
+fn reactivate_employee(nip: u64) -> Result<Employee, Error> {
+    set_employee_active(nip, true)
+}
+
+// Set whether an employee takes part in active operations
+fn set_employee_active(nip: u64, is_active: bool) -> Result<Employee, Error> {
+    ensure_admin()?;
+
+    let mut employee = get_employee(nip)?;
+    employee.is_active = is_active;
+    employee.updated_at = Some(time());
+
+    EMPLOYEE_STORAGE.with(|storage| storage.borrow_mut().insert(nip, employee.clone()));
+    certify_employee(nip);
+    Ok(employee)
+}
+
+// Update an existing employee's personal and payroll data
+#[ic_cdk::update]
+fn update_employee(nip: u64, payload: EmployeePayload) -> Result<Employee, Error> {
+    ensure_admin()?;
+
+    let mut employee = EMPLOYEE_STORAGE.with(|storage| storage.borrow().get(&nip)).ok_or_else(|| Error::NotFound {
+        msg: format!("Employee with NIP={} not found", nip),
+    })?;
+
+    // Input validation
+    validate_employee_payload(&payload, Some(&employee))?;
+    ensure_wallet_unused(&payload.wallet_address, Some(nip))?;
+
+    if employee.age != payload.age {
+        employee.pension_age = calculate_pension_age();
+        employee.years_to_pension = years_until_pension(payload.age);
+    }
+    let wage_per_hour = to_minor_units(payload.wage_per_hour);
+    let wage_changed = employee.wage_per_hour != wage_per_hour;
+    if wage_changed && get_wage_history(nip).is_empty() {
+        // Employees created before wage history existed need their original wage recorded
+        record_wage_change(nip, employee.created_at, employee.wage_per_hour);
+    }
+    employee.name = validate_name(&payload.name)?;
+    employee.age = payload.age;
+    employee.wage_per_hour = wage_per_hour;
+    (employee.employment_type, employee.monthly_salary) = resolve_pay_model(&payload, Some(&employee));
+    (employee.wallet_kind, employee.wallet_address) = validate_payout_wallet(&payload.wallet_address)?;
+    if let Some(currency) = &payload.currency {
+        employee.currency = validate_currency(currency)?;
+    }
+    let now = time();
+    employee.updated_at = Some(now);
+    ensure_fits_storage(&employee)?;
+
+    EMPLOYEE_STORAGE.with(|storage| storage.borrow_mut().insert(nip, employee.clone()));
+    certify_employee(nip);
+    if wage_changed {
+        record_wage_change(nip, now, wage_per_hour);
+    }
+    Ok(employee)
+}
+
+// Delete an employee along with their attendance and approval records
+#[ic_cdk::update]
+fn delete_employee(nip: u64) -> Result<Employee, Error> {
+    ensure_admin()?;
+
+    let employee = EMPLOYEE_STORAGE.with(|storage| storage.borrow_mut().remove(&nip)).ok_or_else(|| Error::NotFound {
+        msg: format!("Employee with NIP={} not found", nip),
+    })?;
+    certify_employee(nip);
+
+    // Collect the keys first since the maps can't be mutated while iterating
+    ATTENDANCE_STORAGE.with(|storage| {
+        let keys: Vec<(u64, u64)> = storage.borrow().range((nip, 0)..=(nip, u64::MAX)).map(|(key, _)| key).collect();
+        let mut storage = storage.borrow_mut();
+        for key in keys {
+            storage.remove(&key);
+        }
+    });
+
+    APPROVAL_STORAGE.with(|storage| {
+        let approvals: Vec<((u64, u64), PayrollApproval)> = storage.borrow().range((nip, 0)..=(nip, u64::MAX)).collect();
+        let mut storage = storage.borrow_mut();
+        for (key, approval) in approvals {
+            storage.remove(&key);
+            log_approval_removal(key.0, key.1, approval.status);
+        }
+    });
+
+    SK_CHUNK_STORAGE.with(|storage| remove_sk_chunks(storage, nip));
+    SK_FILE_STORAGE.with(|storage| storage.borrow_mut().remove(&nip));
+    SK_FILE_CHUNKS.with(|storage| remove_sk_chunks(storage, nip));
+
+    WAGE_HISTORY.with(|history| {
+        let keys: Vec<(u64, u64)> = history.borrow().range((nip, 0)..=(nip, u64::MAX)).map(|(key, _)| key).collect();
+        let mut history = history.borrow_mut();
+        for key in keys {
+            history.remove(&key);
+        }
+    });
+
+    LEAVE_STORAGE.with(|storage| {
+        let keys: Vec<(u64, u64)> = storage.borrow().range((nip, 0)..=(nip, u64::MAX)).map(|(key, _)| key).collect();
+        let mut storage = storage.borrow_mut();
+        for key in keys {
+            storage.remove(&key);
+        }
+    });
+
+    CORRECTION_STORAGE.with(|storage| {
+        let keys: Vec<(u64, u64)> = storage.borrow().range((nip, 0)..=(nip, u64::MAX)).map(|(key, _)| key).collect();
+        let mut storage = storage.borrow_mut();
+        for key in keys {
+            storage.remove(&key);
+        }
+    });
+
+    Ok(employee)
+}
+
+// Move an employee and every record keyed by their NIP to a corrected NIP
+#[ic_cdk::update]
+fn reassign_nip(old_nip: u64, new_nip: u64) -> Result<(), Error> {
+    ensure_admin()?;
+    if old_nip == new_nip {
+        return Err(Error::InvalidInput {
+            msg: "New NIP is the same as the current NIP".to_string(),
+        });
+    }
+    let mut employee = get_employee(old_nip)?;
+    if EMPLOYEE_STORAGE.with(|storage| storage.borrow().contains_key(&new_nip)) {
+        return Err(Error::InvalidInput {
+            msg: format!("Employee with NIP={} already exists", new_nip),
+        });
+    }
+    let next_nip = next_nip_after(new_nip)?;
+
+    employee.nip = new_nip;
+    employee.updated_at = Some(time());
+    EMPLOYEE_STORAGE.with(|storage| {
+        let mut storage = storage.borrow_mut();
+        storage.remove(&old_nip);
+        storage.insert(new_nip, employee);
+    });
+    ID_COUNTER.with(|counter| {
+        if *counter.borrow().get() < next_nip {
+            counter.borrow_mut().set(next_nip).expect("Cannot increment ID counter");
+        }
+    });
+    certify_employee(old_nip);
+    certify_employee(new_nip);
+
+    ATTENDANCE_STORAGE.with(|storage| rekey_nip_records(storage, old_nip, new_nip, |_| ()));
+    APPROVAL_STORAGE.with(|storage| {
+        rekey_nip_records(storage, old_nip, new_nip, |approval| approval.employee_nip = new_nip)
+    });
+    WAGE_HISTORY.with(|history| rekey_nip_records(history, old_nip, new_nip, |_| ()));
+    LEAVE_STORAGE.with(|storage| rekey_nip_records(storage, old_nip, new_nip, |_| ()));
+    CORRECTION_STORAGE.with(|storage| rekey_nip_records(storage, old_nip, new_nip, |_| ()));
+
+    SK_CHUNK_STORAGE.with(|storage| rekey_sk_chunks(storage, old_nip, new_nip));
+    SK_FILE_CHUNKS.with(|storage| rekey_sk_chunks(storage, old_nip, new_nip));
+    SK_FILE_STORAGE.with(|storage| {
+        let mut storage = storage.borrow_mut();
+        if let Some(mut sk_file) = storage.remove(&old_nip) {
+            sk_file.id = new_nip;
+            storage.insert(new_nip, sk_file);
+        }
+    });
+
+    // Keep the audit trail attached to the person rather than the retired NIP
+    APPROVAL_LOG.with(|log| {
+        let entries: Vec<(u64, ApprovalEvent)> =
+            log.borrow().iter().filter(|(_, event)| event.employee_nip == old_nip).collect();
+        let mut log = log.borrow_mut();
+        for (id, mut event) in entries {
+            event.employee_nip = new_nip;
+            log.insert(id, event);
+        }
+    });
+    rekey_processed_requests(old_nip, new_nip);
+
+    Ok(())
+}
+
+// Point client request ids recorded for one NIP at another, so retries still find their attendance
+fn rekey_processed_requests(old_nip: u64, new_nip: u64) {
+    PROCESSED_REQUESTS.with(|requests| {
+        let entries: Vec<(Blob<MAX_CLIENT_REQUEST_ID_LENGTH>, ProcessedRequest)> =
+            requests.borrow().iter().filter(|(_, request)| request.nip == old_nip).collect();
+        let mut requests = requests.borrow_mut();
+        for (key, mut request) in entries {
+            request.nip = new_nip;
+            requests.insert(key, request);
+        }
+    });
+}
+
+// Move every (nip, day) record from one NIP to another, adjusting each value as it moves
+fn rekey_nip_records<V: BoundedStorable>(
+    storage: &RefCell<StableBTreeMap<(u64, u64), V, Memory>>,
+    old_nip: u64,
+    new_nip: u64,
+    adjust: impl Fn(&mut V),
+) {
+    // Collect first since the map can't be mutated while iterating
+    let records: Vec<((u64, u64), V)> = storage.borrow().range((old_nip, 0)..=(old_nip, u64::MAX)).collect();
+    let mut storage = storage.borrow_mut();
+    for ((_, day), mut value) in records {
+        storage.remove(&(old_nip, day));
+        adjust(&mut value);
+        storage.insert((new_nip, day), value);
+    }
+}
+
+// Move every (nip, chunk_index) SK chunk from one NIP to another
+fn rekey_sk_chunks(storage: &RefCell<StableBTreeMap<(u64, u32), SkChunk, Memory>>, old_nip: u64, new_nip: u64) {
+    let chunks: Vec<((u64, u32), SkChunk)> = storage.borrow().range((old_nip, 0)..=(old_nip, u32::MAX)).collect();
+    let mut storage = storage.borrow_mut();
+    for ((_, chunk_index), chunk) in chunks {
+        storage.remove(&(old_nip, chunk_index));
+        storage.insert((new_nip, chunk_index), chunk);
+    }
+}
+
+// Remove every SK chunk stored for a NIP
+fn remove_sk_chunks(storage: &RefCell<StableBTreeMap<(u64, u32), SkChunk, Memory>>, nip: u64) {
+    // Collect the keys first since the map can't be mutated while iterating
+    let keys: Vec<(u64, u32)> = storage.borrow().range((nip, 0)..=(nip, u32::MAX)).map(|(key, _)| key).collect();
+    let mut storage = storage.borrow_mut();
+    for key in keys {
+        storage.remove(&key);
+    }
+}
+
+// Stage one chunk of an employee's SK file; only the admin or the employee may upload it
+#[ic_cdk::update]
+fn upload_sk_chunk(nip: u64, chunk_index: u32, data: Vec<u8>) -> Result<(), Error> {
+    ensure_admin_or_employee(&get_employee(nip)?)?;
+
+    if data.is_empty() || data.len() > MAX_SK_CHUNK_SIZE as usize {
+        return Err(Error::InvalidInput {
+            msg: format!("Chunk size must be between 1 and {} bytes", MAX_SK_CHUNK_SIZE),
+        });
+    }
+
+    SK_CHUNK_STORAGE.with(|storage| {
+        let mut storage = storage.borrow_mut();
+        let (staged_count, staged_size) = storage
+            .range((nip, 0)..=(nip, u32::MAX))
+            .fold((0u32, 0usize), |(count, size), (_, chunk)| (count + 1, size + chunk.data.len()));
+
+        // Chunks must arrive in order; re-sending an already staged index replaces it
+        if chunk_index > staged_count {
+            return Err(Error::InvalidInput {
+                msg: format!("Expected chunk {} but received chunk {}", staged_count, chunk_index),
+            });
+        }
+
+        let replaced_size = storage.get(&(nip, chunk_index)).map_or(0, |chunk| chunk.data.len());
+        if staged_size - replaced_size + data.len() > MAX_SK_FILE_SIZE as usize {
+            return Err(Error::InvalidInput {
+                msg: format!("SK file exceeds the maximum size of {} bytes", MAX_SK_FILE_SIZE),
+            });
+        }
+
+        storage.insert((nip, chunk_index), SkChunk { data });
+        Ok(())
+    })
+}
+
+// Assemble the staged chunks into the employee's SK file; only the admin or the employee may.
+// Returns the file's metadata only: a file near MAX_SK_FILE_SIZE would not fit in the reply.
+#[ic_cdk::update]
+fn finalize_sk_upload(nip: u64) -> Result<SkFileInfo, Error> {
+    ensure_admin_or_employee(&get_employee(nip)?)?;
+
+    let chunks: Vec<((u64, u32), SkChunk)> =
+        SK_CHUNK_STORAGE.with(|storage| storage.borrow().range((nip, 0)..=(nip, u32::MAX)).collect());
+    if chunks.is_empty() {
+        return Err(Error::NotFound {
+            msg: format!("No SK chunks uploaded for NIP={}", nip),
+        });
+    }
+
+    let mut size = 0;
+    for (expected_index, ((_, chunk_index), chunk)) in chunks.iter().enumerate() {
+        if *chunk_index as usize != expected_index {
+            return Err(Error::InvalidInput {
+                msg: format!("SK chunk {} is missing", expected_index),
+            });
+        }
+        size += chunk.data.len() as u64;
+    }
+
+    let info = SkFileInfo {
+        id: nip,
+        chunk_count: chunks.len() as u32,
+        size,
+        is_verified: false,
+        created_at: time(),
+        updated_at: None,
+    };
+
+    // Finalized chunks keep the staged indexes, replacing any earlier file
+    SK_FILE_CHUNKS.with(|storage| {
+        remove_sk_chunks(storage, nip);
+        let mut storage = storage.borrow_mut();
+        for (key, chunk) in &chunks {
+            storage.insert(*key, chunk.clone());
+        }
+    });
+    SK_FILE_STORAGE.with(|storage| storage.borrow_mut().insert(nip, info.clone()));
+    EMPLOYEE_STORAGE.with(|storage| {
+        let mut storage = storage.borrow_mut();
+        if let Some(mut employee) = storage.get(&nip) {
+            employee.has_sk_file = true;
+            storage.insert(nip, employee);
+        }
+    });
+    certify_employee(nip);
+    SK_CHUNK_STORAGE.with(|storage| remove_sk_chunks(storage, nip));
+
+    Ok(info)
+}
+
+// Retrieve an employee's SK file in one reply; files too large for a reply are read
+// piecewise with get_sk_file_chunk
+#[ic_cdk::query]
+fn get_sk_file(nip: u64) -> Result<PdfFile, Error> {
+    let info = get_sk_file_info(nip)?;
+    let content = SK_FILE_CHUNKS.with(|storage| {
+        storage
+            .borrow()
+            .range((nip, 0)..=(nip, u32::MAX))
+            .flat_map(|(_, chunk)| chunk.data)
+            .collect()
+    });
+    Ok(assemble_sk_file(info, content))
+}
+
+// Read up to len bytes of an employee's SK file starting at offset; an empty result marks the end.
+// len is capped at MAX_SK_CHUNK_SIZE so every read fits in a reply.
+#[ic_cdk::query]
+fn get_sk_file_chunk(nip: u64, offset: u64, len: u32) -> Result<Vec<u8>, Error> {
+    if len > MAX_SK_CHUNK_SIZE {
+        return Err(Error::InvalidInput {
+            msg: format!("Cannot read more than {} bytes at once", MAX_SK_CHUNK_SIZE),
+        });
+    }
+    get_sk_file_info(nip)?;
+
+    let end = offset.saturating_add(u64::from(len));
+    let mut data = Vec::new();
+    let mut chunk_start = 0;
+    SK_FILE_CHUNKS.with(|storage| {
+        for (_, chunk) in storage.borrow().range((nip, 0)..=(nip, u32::MAX)) {
+            let chunk_end = chunk_start + chunk.data.len() as u64;
+            if chunk_end > offset && chunk_start < end {
+                let from = offset.saturating_sub(chunk_start) as usize;
+                let to = (end.min(chunk_end) - chunk_start) as usize;
+                data.extend_from_slice(&chunk.data[from..to]);
+            }
+            if chunk_end >= end {
+                break;
+            }
+            chunk_start = chunk_end;
+        }
+    });
+    Ok(data)
+}
+
+// Retrieve the metadata of an employee's SK file
+fn get_sk_file_info(nip: u64) -> Result<SkFileInfo, Error> {
+    SK_FILE_STORAGE.with(|storage| storage.borrow().get(&nip)).ok_or_else(|| Error::NotFound {
+        msg: format!("SK file for NIP={} not found", nip),
+    })
+}
+
+// Combine SK file metadata with the file's bytes
+fn assemble_sk_file(info: SkFileInfo, content: Vec<u8>) -> PdfFile {
+    PdfFile {
+        id: info.id,
+        content,
+        is_verified: info.is_verified,
+        created_at: info.created_at,
+        updated_at: info.updated_at,
+    }
+}
+
+// Remove an employee's SK file so a corrected one can be uploaded.
+// A replacement goes through finalize_sk_upload again and starts out unverified.
+#[ic_cdk::update]
+fn delete_sk_file(nip: u64) -> Result<(), Error> {
+    ensure_admin()?;
+    SK_FILE_STORAGE
+        .with(|storage| storage.borrow_mut().remove(&nip))
+        .ok_or_else(|| Error::NotFound {
+            msg: format!("SK file for NIP={} not found", nip),
+        })?;
+    SK_FILE_CHUNKS.with(|storage| remove_sk_chunks(storage, nip));
+
+    EMPLOYEE_STORAGE.with(|storage| {
+        let mut storage = storage.borrow_mut();
+        if let Some(mut employee) = storage.get(&nip) {
+            employee.has_sk_file = false;
+            storage.insert(nip, employee);
+        }
+    });
+    certify_employee(nip);
+
+    Ok(())
+}
+
+// Mark an employee's SK file as verified
+#[ic_cdk::update]
+fn verify_sk_file(nip: u64) -> Result<SkFileInfo, Error> {
+    ensure_admin()?;
+    let mut info = get_sk_file_info(nip)?;
+    info.is_verified = true;
+    info.updated_at = Some(time());
+
+    SK_FILE_STORAGE.with(|storage| storage.borrow_mut().insert(nip, info.clone()));
+    Ok(info)
+}
+
+// List active employees whose SK file is missing or not yet verified
+#[ic_cdk::query]
+fn employees_with_unverified_sk() -> Vec<u64> {
+    EMPLOYEE_STORAGE.with(|employees| {
+        employees
+            .borrow()
+            .iter()
+            .filter(|(_, employee)| employee.is_active)
+            .map(|(nip, _)| nip)
+            .filter(|nip| {
+                let verified = SK_FILE_STORAGE.with(|storage| storage.borrow().get(nip).map(|sk_file| sk_file.is_verified));
+                verified != Some(true)
+            })
+            .collect()
+    })
+}
+
+// Validate a shift and compute its hours and wages for the given employee
+fn calculate_attendance(employee: &Employee, check_in: u64, check_out: u64) -> Result<Attendance, Error> {
+    let now = time();
+    if check_in == 0 {
+        return Err(Error::InvalidInput {
+            msg: "check_in must be set".to_string(),
+        });
+    }
+    if check_in > now || check_out > now {
+        return Err(Error::InvalidInput {
+            msg: "check_in and check_out cannot be in the future".to_string(),
+        });
+    }
+    if check_out < check_in {
+        return Err(Error::InvalidInput {
+            msg: "check_out is before check_in".to_string(),
+        });
+    }
+    if check_out == check_in {
+        return Err(Error::InvalidInput {
+            msg: "check_in equals check_out".to_string(),
+        });
+    }
+
+    validate_shift_length(check_in, check_out)?;
+
+    // Pay the rate that applied when the shift started, not the current one
+    Ok(price_shift(employee, check_in, check_out, wage_in_effect(employee, check_in)))
+}
+
+// Ensure a shift is no longer than the configured maximum; a shift of exactly the maximum is allowed
+fn validate_shift_length(check_in: u64, check_out: u64) -> Result<(), Error> {
+    let max_shift_hours = get_max_shift_hours();
+    // Compare whole nanoseconds so the boundary doesn't depend on float rounding
+    let max_shift_nanos = (max_shift_hours * NANOS_PER_HOUR as f64) as u64;
+    if check_out.saturating_sub(check_in) > max_shift_nanos {
+        return Err(Error::InvalidInput {
+            msg: format!(
+                "Shift of {} hours exceeds the maximum of {} hours",
+                calculate_work_hours(check_in, check_out),
+                max_shift_hours
+            ),
+        });
+    }
+    Ok(())
+}
+
+// Compute the wages and deductions for a validated shift.
+// Hourly employees earn the given hourly wage; salaried employees earn a fixed daily share of their salary.
+fn price_shift(employee: &Employee, check_in: u64, check_out: u64, wage_per_hour: Money) -> Attendance {
+    let total_hours = calculate_work_hours(check_in, check_out);
+    if employee.employment_type == EmploymentType::Salaried {
+        let daily_wage = salaried_daily_wage(employee.monthly_salary);
+        return build_attendance(check_in, check_out, total_hours, daily_wage, daily_wage);
+    }
+
+    let worked_nanos = check_out - check_in;
+    let overtime_threshold_nanos = standard_day_nanos();
+    let daily_wage = calculate_daily_wage_with_overtime(
+        worked_nanos,
+        wage_per_hour,
+        overtime_threshold_nanos,
+        OVERTIME_MULTIPLIER_PERCENT,
+    );
+    let regular_wage = calculate_daily_wage(worked_nanos.min(overtime_threshold_nanos), wage_per_hour);
+    build_attendance(check_in, check_out, total_hours, regular_wage, daily_wage)
+}
+
+// Assemble an attendance record, applying deductions to the day's gross wage
+fn build_attendance(
+    check_in: u64,
+    check_out: u64,
+    total_hours: f64,
+    regular_wage: Money,
+    daily_wage: Money,
+) -> Attendance {
+    let rates = get_deduction_rates();
+
+    Attendance {
+        check_in,
+        check_out,
+        total_hours,
+        regular_wage,
+        overtime_wage: daily_wage - regular_wage,
+        daily_wage,
+        tax_amount: apply_rate(daily_wage, rates.tax_rate),
+        pension_amount: apply_rate(daily_wage, rates.pension_rate),
+        net_wage: calculate_net_wage(daily_wage, rates.tax_rate, rates.pension_rate),
+        late_by_minutes: late_by_minutes(check_in),
+    }
+}
+
+// Minutes a check-in came after the scheduled start plus the grace period, 0 if on time
+fn late_by_minutes(check_in: u64) -> u64 {
+    let schedule = get_shift_schedule();
+    match schedule.start_minute {
+        Some(start_minute) => {
+            let check_in_minute = (check_in % NANOS_PER_DAY) / NANOS_PER_MINUTE;
+            check_in_minute.saturating_sub(u64::from(start_minute) + u64::from(schedule.grace_minutes))
+        }
+        None => 0,
+    }
+}
+
+// Record attendance for an employee.
+// check_in and check_out are nanoseconds since the Unix epoch, the same unit as time().
+// The shift is filed under the day its check_in falls on; days outside the configured
+// workweek are refused unless allow_non_workday is set.
+#[ic_cdk::update]
+fn record_attendance(
+    nip: u64,
+    check_in: u64,
+    check_out: u64,
+    client_request_id: Option<String>,
+    allow_non_workday: bool,
+) -> Result<Attendance, Error> {
+    let request_key = client_request_id.as_deref().map(client_request_key).transpose()?;
+    if let Some(key) = &request_key {
+        if let Some(attendance) = previously_recorded_attendance(key, nip)? {
+            return Ok(attendance);
+        }
+    }
+
+    let employee = get_active_employee(nip)?;
+    let attendance = calculate_attendance(&employee, check_in, check_out)?;
+    let date = store_attendance(nip, &attendance, allow_non_workday)?;
+    if let Some(key) = request_key {
+        let request = ProcessedRequest {
+            nip,
+            date,
+            processed_at: time(),
+        };
+        PROCESSED_REQUESTS.with(|requests| requests.borrow_mut().insert(key, request));
+    }
+
+    Ok(attendance)
+}
+
+// File a validated shift under the day its check-in falls on, returning that day's key.
+// A shift that runs past midnight still belongs to the day it started.
+fn store_attendance(nip: u64, attendance: &Attendance, allow_non_workday: bool) -> Result<u64, Error> {
+    let date = day_key(attendance.check_in);
+    validate_attendance_day(nip, date, allow_non_workday)?;
+    ensure_fits_storage(attendance)?;
+
+    ATTENDANCE_STORAGE.with(|storage| storage.borrow_mut().insert((nip, date), attendance.clone()));
+    Ok(date)
+}
+
+// Check that a shift may be filed for the employee on the given day
+fn validate_attendance_day(nip: u64, date: u64, allow_non_workday: bool) -> Result<(), Error> {
+    if ATTENDANCE_STORAGE.with(|storage| storage.borrow().contains_key(&(nip, date))) {
+        return Err(Error::InvalidInput {
+            msg: "Attendance already recorded for that day".to_string(),
+        });
+    }
+    if LEAVE_STORAGE.with(|storage| storage.borrow().contains_key(&(nip, date))) {
+        return Err(Error::InvalidInput {
+            msg: "Leave is already recorded for that day".to_string(),
+        });
+    }
+    if !allow_non_workday && !get_workweek().contains(&day_of_week(date)) {
+        return Err(Error::InvalidInput {
+            msg: "That day is not a workday; pass allow_non_workday to record it anyway".to_string(),
+        });
+    }
+    Ok(())
+}
+
+// Compute the attendance a shift would produce without recording it.
+// The shift is checked the same way record_attendance checks it, so a preview
+// succeeds only when recording the same shift would.
+#[ic_cdk::query]
+fn preview_wage(nip: u64, check_in: u64, check_out: u64, allow_non_workday: bool) -> Result<Attendance, Error> {
+    let employee = get_active_employee(nip)?;
+    let attendance = calculate_attendance(&employee, check_in, check_out)?;
+    validate_attendance_day(nip, day_key(check_in), allow_non_workday)?;
+    ensure_fits_storage(&attendance)?;
+    Ok(attendance)
+}
+
+// Retrieve an employee who can still record attendance
+fn get_active_employee(nip: u64) -> Result<Employee, Error> {
+    let employee = get_employee(nip)?;
+    if !employee.is_active {
+        return Err(Error::InvalidInput {
+            msg: format!("Employee with NIP={} is archived", nip),
+        });
+    }
+    Ok(employee)
+}
+
+// Convert a client request id into its storage key
+fn client_request_key(client_request_id: &str) -> Result<Blob<MAX_CLIENT_REQUEST_ID_LENGTH>, Error> {
+    if client_request_id.is_empty() {
+        return Err(Error::InvalidInput {
+            msg: "client_request_id cannot be empty".to_string(),
+        });
+    }
+    Blob::try_from(client_request_id.as_bytes()).map_err(|_| Error::InvalidInput {
+        msg: format!("client_request_id cannot be longer than {} bytes", MAX_CLIENT_REQUEST_ID_LENGTH),
+    })
+}
+
+// Look up the attendance an earlier call with the same client request id recorded.
+// Returns None when the id is new or its attendance has since been deleted.
+fn previously_recorded_attendance(
+    key: &Blob<MAX_CLIENT_REQUEST_ID_LENGTH>,
+    nip: u64,
+) -> Result<Option<Attendance>, Error> {
+    let request = match PROCESSED_REQUESTS.with(|requests| requests.borrow().get(key)) {
+        Some(request) => request,
+        None => return Ok(None),
+    };
+    if request.nip != nip {
+        return Err(Error::InvalidInput {
+            msg: "client_request_id was already used for another employee".to_string(),
+        });
+    }
+    Ok(ATTENDANCE_STORAGE.with(|storage| storage.borrow().get(&(request.nip, request.date))))
+}
+
+// Forget client request ids older than CLIENT_REQUEST_TTL_NANOS
+fn prune_processed_requests() {
+    let cutoff = time().saturating_sub(CLIENT_REQUEST_TTL_NANOS);
+    PROCESSED_REQUESTS.with(|requests| {
+        let keys: Vec<Blob<MAX_CLIENT_REQUEST_ID_LENGTH>> = requests
+            .borrow()
+            .iter()
+            .filter(|(_, request)| request.processed_at < cutoff)
+            .map(|(key, _)| key)
+            .collect();
+        let mut requests = requests.borrow_mut();
+        for key in keys {
+            requests.remove(&key);
+        }
+    });
+}
+
+// Prune expired client request ids periodically; timers don't survive upgrades
+fn schedule_request_id_pruning() {
+    ic_cdk_timers::set_timer_interval(
+        Duration::from_nanos(CLIENT_REQUEST_PRUNE_INTERVAL_NANOS),
+        prune_processed_requests,
+    );
+}
+
+// Correct a recorded shift, recomputing wages at the rate in effect when it started.
+// Payroll that has already been approved or paid can no longer be changed.
+#[ic_cdk::update]
+fn update_attendance(nip: u64, date: u64, check_in: u64, check_out: u64) -> Result<Attendance, Error> {
+    ensure_admin_or_manager()?;
+    let employee = get_employee(nip)?;
+    get_attendance(nip, date)?;
+    ensure_payroll_unlocked(nip, date)?;
+    ensure_check_in_on(check_in, date)?;
+
+    let attendance = calculate_attendance(&employee, check_in, check_out)?;
+    ATTENDANCE_STORAGE.with(|storage| storage.borrow_mut().insert((nip, date), attendance.clone()));
+    refresh_pending_wage(nip, date, attendance.daily_wage);
+
+    Ok(attendance)
+}
+
+// Re-price a recorded shift at the employee's current hourly wage, e.g. after a raise.
+// Payroll that has already been approved or paid can no longer be changed.
+#[ic_cdk::update]
+fn recalculate_daily_wage(nip: u64, date: u64) -> Result<Attendance, Error> {
+    ensure_admin_or_manager()?;
+    let employee = get_employee(nip)?;
+    let recorded = get_attendance(nip, date)?;
+    ensure_payroll_unlocked(nip, date)?;
+    if recorded.check_out <= recorded.check_in {
+        return Err(Error::InvalidInput {
+            msg: format!("Attendance for NIP={} on date {} has no completed shift", nip, date),
+        });
+    }
+
+    let attendance = price_shift(&employee, recorded.check_in, recorded.check_out, employee.wage_per_hour);
+    ATTENDANCE_STORAGE.with(|storage| storage.borrow_mut().insert((nip, date), attendance.clone()));
+    refresh_pending_wage(nip, date, attendance.daily_wage);
+
+    Ok(attendance)
+}
+
+// Propose new check-in and check-out times for a recorded shift; a manager applies them.
+// Only the employee may propose, and a later submission for the same day replaces the earlier one.
+#[ic_cdk::update]
+fn submit_correction(
+    nip: u64,
+    date: u64,
+    proposed_check_in: u64,
+    proposed_check_out: u64,
+) -> Result<CorrectionRequest, Error> {
+    let employee = get_employee(nip)?;
+    ensure_caller_is_employee(&employee)?;
+    get_attendance(nip, date)?;
+    ensure_payroll_unlocked(nip, date)?;
+    ensure_check_in_on(proposed_check_in, date)?;
+    // Validate the proposal now so the manager only sees shifts that can be applied
+    calculate_attendance(&employee, proposed_check_in, proposed_check_out)?;
+
+    let correction = CorrectionRequest {
+        proposed_check_in,
+        proposed_check_out,
+        requested_by: ic_cdk::caller().to_text(),
+        requested_at: time(),
+    };
+    CORRECTION_STORAGE.with(|storage| storage.borrow_mut().insert((nip, date), correction.clone()));
+    Ok(correction)
+}
+
+// Retrieve the correction waiting for an employee's working day
+#[ic_cdk::query]
+fn get_correction_request(nip: u64, date: u64) -> Result<CorrectionRequest, Error> {
+    CORRECTION_STORAGE.with(|storage| storage.borrow().get(&(nip, date))).ok_or_else(|| Error::NotFound {
+        msg: format!("Correction request for NIP={} on date {} not found", nip, date),
+    })
+}
+
+// Overwrite a recorded shift with its submitted correction.
+// The caller must be an allowlisted manager or the manager assigned to the day's approval.
+#[ic_cdk::update]
+fn apply_correction(nip: u64, date: u64) -> Result<Attendance, Error> {
+    let correction = get_correction_request(nip, date)?;
+    match APPROVAL_STORAGE.with(|storage| storage.borrow().get(&(nip, date))) {
+        Some(approval) => ensure_caller_can_approve(&approval.manager_wallet)?,
+        None => {
+            let caller = principal_key(&ic_cdk::caller());
+            if !MANAGERS.with(|managers| managers.borrow().contains_key(&caller)) {
+                return Err(Error::Unauthorized {
+                    msg: "Caller is not a manager".to_string(),
+                });
+            }
+        }
+    }
+
+    let employee = get_employee(nip)?;
+    get_attendance(nip, date)?;
+    ensure_payroll_unlocked(nip, date)?;
+    ensure_check_in_on(correction.proposed_check_in, date)?;
+
+    let attendance = calculate_attendance(&employee, correction.proposed_check_in, correction.proposed_check_out)?;
+    ATTENDANCE_STORAGE.with(|storage| storage.borrow_mut().insert((nip, date), attendance.clone()));
+    refresh_pending_wage(nip, date, attendance.daily_wage);
+    CORRECTION_STORAGE.with(|storage| storage.borrow_mut().remove(&(nip, date)));
+
+    Ok(attendance)
+}
+
+// Remove a recorded shift along with any approval request still pending for it.
+// Shifts whose payroll has been decided are kept for payroll integrity.
+#[ic_cdk::update]
+fn delete_attendance(nip: u64, date: u64) -> Result<Attendance, Error> {
+    ensure_admin_or_manager()?;
+    let approval = APPROVAL_STORAGE.with(|storage| storage.borrow().get(&(nip, date)));
+    if let Some(approval) = &approval {
+        if approval.status != ApprovalStatus::Pending {
+            return Err(Error::InvalidInput {
+                msg: format!("Payroll for NIP={} on date {} has already been decided", nip, date),
+            });
+        }
+    }
+
+    let attendance = ATTENDANCE_STORAGE.with(|storage| storage.borrow_mut().remove(&(nip, date))).ok_or_else(|| Error::NotFound {
+        msg: format!("Attendance for NIP={} on date {} not found", nip, date),
+    })?;
+    if let Some(approval) = approval {
+        APPROVAL_STORAGE.with(|storage| storage.borrow_mut().remove(&(nip, date)));
+        log_approval_removal(nip, date, approval.status);
+    }
+
+    Ok(attendance)
+}
+
+// Ensure a shift starts on the day it is filed under, so no day's key holds another day's shift
+fn ensure_check_in_on(check_in: u64, date: u64) -> Result<(), Error> {
+    if day_key(check_in) != date {
+        return Err(Error::InvalidInput {
+            msg: format!("check_in falls on day {}, not on date {}", day_key(check_in), date),
+        });
+    }
+    Ok(())
+}
+
+// Ensure the payroll for a date has not been approved or paid yet
+fn ensure_payroll_unlocked(nip: u64, date: u64) -> Result<(), Error> {
+    let status = APPROVAL_STORAGE.with(|storage| storage.borrow().get(&(nip, date)).map(|approval| approval.status));
+    match status {
+        Some(ApprovalStatus::Approved) | Some(ApprovalStatus::Paid { .. }) => Err(Error::InvalidInput {
+            msg: format!("Payroll for NIP={} on date {} is already approved", nip, date),
+        }),
+        _ => Ok(()),
+    }
+}
+
+// Keep a pending approval's wage amount in sync with corrected attendance
+fn refresh_pending_wage(nip: u64, date: u64, daily_wage: Money) {
+    APPROVAL_STORAGE.with(|storage| {
+        let mut storage = storage.borrow_mut();
+        if let Some(mut approval) = storage.get(&(nip, date)) {
+            if approval.status == ApprovalStatus::Pending {
+                approval.wage_amount = daily_wage;
+                storage.insert((nip, date), approval);
+            }
+        }
+    });
+}
+
+// Retrieve an employee's attendance for a day key (days since the Unix epoch)
+#[ic_cdk::query]
+fn get_attendance(nip: u64, date: u64) -> Result<Attendance, Error> {
+    ATTENDANCE_STORAGE.with(|storage| {
+        storage.borrow().get(&(nip, date)).ok_or(Error::NotFound {
+            msg: format!("Attendance for NIP={} on date {} not found", nip, date),
+        })
+    })
+}
+
+// Retrieve an employee's attendance records as (date, attendance) pairs, oldest first
+#[ic_cdk::query]
+fn get_attendance_history(nip: u64) -> Vec<(u64, Attendance)> {
+    ATTENDANCE_STORAGE.with(|storage| {
+        storage
+            .borrow()
+            .range((nip, 0)..=(nip, u64::MAX))
+            .map(|((_, date), attendance)| (date, attendance))
+            .collect()
+    })
+}
+
+// Retrieve everyone's attendance for a day as (nip, attendance) pairs.
+// The map is keyed by NIP first, so this scans every record.
+#[ic_cdk::query]
+fn attendance_on_date(date: u64) -> Vec<(u64, Attendance)> {
+    ATTENDANCE_STORAGE.with(|storage| {
+        storage
+            .borrow()
+            .iter()
+            .filter(|((_, day), _)| *day == date)
+            .map(|((nip, _), attendance)| (nip, attendance))
+            .collect()
+    })
+}
+
+// Sum the gross daily wages of everyone who worked on a day, whatever their approval status
+#[ic_cdk::query]
+fn payroll_cost_for_date(date: u64) -> Money {
+    ATTENDANCE_STORAGE.with(|storage| {
+        storage
+            .borrow()
+            .iter()
+            .filter(|((_, day), _)| *day == date)
+            .map(|(_, attendance)| attendance.daily_wage)
+            .sum()
+    })
+}
+
+// List the NIPs whose attendance for a day is missing its check-out or has no hours worked
+#[ic_cdk::query]
+fn incomplete_attendance(date: u64) -> Vec<u64> {
+    ATTENDANCE_STORAGE.with(|storage| {
+        storage
+            .borrow()
+            .iter()
+            .filter(|((_, day), attendance)| {
+                *day == date && (attendance.check_out == 0 || attendance.total_hours <= 0.0)
+            })
+            .map(|((nip, _), _)| nip)
+            .collect()
+    })
+}
+
+// Record a day of leave for an employee; paid leave earns a standard day at the wage in effect
+#[ic_cdk::update]
+fn record_leave(nip: u64, date: u64, leave_type: LeaveType) -> Result<LeaveRecord, Error> {
+    ensure_admin()?;
+    let employee = get_active_employee(nip)?;
+
+    if ATTENDANCE_STORAGE.with(|storage| storage.borrow().contains_key(&(nip, date))) {
+        return Err(Error::InvalidInput {
+            msg: format!("Attendance is already recorded for NIP={} on date {}", nip, date),
+        });
+    }
+    if LEAVE_STORAGE.with(|storage| storage.borrow().contains_key(&(nip, date))) {
+        return Err(Error::InvalidInput {
+            msg: format!("Leave is already recorded for NIP={} on date {}", nip, date),
+        });
+    }
+
+    let daily_wage = match leave_type {
+        LeaveType::Sick | LeaveType::Vacation => match employee.employment_type {
+            EmploymentType::Hourly => calculate_daily_wage(
+                standard_day_nanos(),
+                wage_in_effect(&employee, date.saturating_mul(NANOS_PER_DAY)),
+            ),
+            EmploymentType::Salaried => salaried_daily_wage(employee.monthly_salary),
+        },
+        LeaveType::Unpaid => 0,
+    };
+    let leave = LeaveRecord {
+        leave_type,
+        daily_wage,
+        recorded_at: time(),
+    };
+
+    LEAVE_STORAGE.with(|storage| storage.borrow_mut().insert((nip, date), leave.clone()));
+    Ok(leave)
+}
+
+// Retrieve an employee's leave records as (date, leave) pairs, oldest first
+#[ic_cdk::query]
+fn list_leave(nip: u64) -> Vec<(u64, LeaveRecord)> {
+    LEAVE_STORAGE.with(|storage| {
+        storage
+            .borrow()
+            .range((nip, 0)..=(nip, u64::MAX))
+            .map(|((_, date), leave)| (date, leave))
+            .collect()
+    })
+}
+
+// Sum an employee's daily wages over a calendar month
+#[ic_cdk::query]
+fn monthly_payroll_summary(nip: u64, year: u32, month: u32) -> Result<Money, Error> {
+    get_employee(nip)?;
+
+    if !(1..=12).contains(&month) {
+        return Err(Error::InvalidInput {
+            msg: format!("Invalid month {}", month),
+        });
+    }
+
+    Ok(ATTENDANCE_STORAGE.with(|storage| {
+        storage
+            .borrow()
+            .range((nip, 0)..=(nip, u64::MAX))
+            .filter(|((_, date), _)| {
+                let (record_year, record_month, _) = day_key_to_ymd(*date);
+                record_year == year && record_month == month
+            })
+            .map(|(_, attendance)| attendance.daily_wage)
+            .sum()
+    }))
+}
+
+// Sum the hours an employee worked between two day keys, inclusive
+#[ic_cdk::query]
+fn total_hours_in_range(nip: u64, start_date: u64, end_date: u64) -> Result<f64, Error> {
+    if start_date > end_date {
+        return Err(Error::InvalidInput {
+            msg: "start_date must not be after end_date".to_string(),
+        });
+    }
+    get_employee(nip)?;
+
+    Ok(ATTENDANCE_STORAGE.with(|storage| {
+        storage
+            .borrow()
+            .range((nip, start_date)..=(nip, end_date))
+            .map(|(_, attendance)| attendance.total_hours)
+            .sum()
+    }))
+}
+
+// Average the hours an employee worked per recorded day, or 0.0 if nothing is recorded
+#[ic_cdk::query]
+fn average_daily_hours(nip: u64) -> Result<f64, Error> {
+    get_employee(nip)?;
+
+    let (total_hours, days) = ATTENDANCE_STORAGE.with(|storage| {
+        storage
+            .borrow()
+            .range((nip, 0)..=(nip, u64::MAX))
+            .fold((0.0, 0u64), |(total_hours, days), (_, attendance)| {
+                (total_hours + attendance.total_hours, days + 1)
+            })
+    });
+    if days == 0 {
+        return Ok(0.0);
+    }
+    Ok(total_hours / days as f64)
+}
+
+// Request payroll approval for an employee's attendance on a day (the day key of its check-in).
+// The manager must be allowlisted or the default manager, so requesters can't pick their own approver.
+#[ic_cdk::update]
+fn request_approval(nip: u64, date: u64, manager_wallet: String) -> Result<PayrollApproval, Error> {
+    validate_manager_wallet(&manager_wallet)?;
+    let attendance = get_attendance(nip, date)?;
+    store_approval_request(nip, date, &attendance, manager_wallet)
+}
+
+// Request payroll approval for an employee's attendance on a day from the configured default manager
+#[ic_cdk::update]
+fn request_approval_default(nip: u64, date: u64) -> Result<PayrollApproval, Error> {
+    let manager = get_default_manager().ok_or_else(|| Error::InvalidInput {
+        msg: "No default manager configured".to_string(),
+    })?;
+    request_approval(nip, date, manager.to_text())
+}
+
+// Open a pending approval for a day's attendance, assigned to the given manager
+fn store_approval_request(
+    nip: u64,
+    date: u64,
+    attendance: &Attendance,
+    manager_wallet: String,
+) -> Result<PayrollApproval, Error> {
+    let employee = get_employee(nip)?;
+    ensure_approval_replaceable(APPROVAL_STORAGE.with(|storage| storage.borrow().get(&(nip, date))).as_ref())?;
+    let approval = PayrollApproval {
+        employee_nip: nip,
+        attendance_date: date,
+        wage_amount: attendance.daily_wage,
+        currency: employee.currency,
+        status: ApprovalStatus::Pending,
+        manager_wallet,
+        reason: None,
         created_at: time(),
-        updated_at: None,
+        payment: None,
+    };
+
+    let previous = APPROVAL_STORAGE.with(|storage| storage.borrow_mut().insert((nip, date), approval.clone()));
+    log_approval_event(nip, date, previous.map(|previous| previous.status), ApprovalStatus::Pending);
+
+    Ok(approval)
+}
+
+// Ensure a new request may take the place of the day's existing approval, if any.
+// Only a still-pending request can be replaced; rejected ones go through reopen_approval.
+fn ensure_approval_replaceable(existing: Option<&PayrollApproval>) -> Result<(), Error> {
+    match existing {
+        Some(approval) if approval.status != ApprovalStatus::Pending => Err(Error::InvalidInput {
+            msg: format!(
+                "Payroll for NIP={} on date {} has already been decided",
+                approval.employee_nip, approval.attendance_date
+            ),
+        }),
+        _ => Ok(()),
+    }
+}
+
+// Open approval requests for yesterday's attendance that nobody requested approval for
+fn request_missing_approvals() {
+    let config = get_auto_approval_config();
+    if !config.enabled {
+        return;
+    }
+
+    let yesterday = current_day_key().saturating_sub(1);
+    let missing: Vec<(u64, Attendance)> = ATTENDANCE_STORAGE.with(|storage| {
+        storage
+            .borrow()
+            .iter()
+            .filter(|(key, _)| key.1 == yesterday)
+            .filter(|(key, _)| !APPROVAL_STORAGE.with(|approvals| approvals.borrow().contains_key(key)))
+            .map(|((nip, _), attendance)| (nip, attendance))
+            .collect()
+    });
+
+    for (nip, attendance) in missing {
+        // An employee removed since their shift simply gets no request
+        let _ = store_approval_request(nip, yesterday, &attendance, config.manager_wallet.clone());
+    }
+}
+
+// Arm the daily timer that opens missing approval requests
+fn schedule_auto_approval() {
+    let timer_id = ic_cdk_timers::set_timer_interval(Duration::from_nanos(NANOS_PER_DAY), request_missing_approvals);
+    if let Some(previous) = AUTO_APPROVAL_TIMER.with(|timer| timer.borrow_mut().replace(timer_id)) {
+        ic_cdk_timers::clear_timer(previous);
+    }
+}
+
+// Retrieve the settings of the daily approval request job
+#[ic_cdk::query]
+fn get_auto_approval_config() -> AutoApprovalConfig {
+    AUTO_APPROVAL_CONFIG.with(|cell| cell.borrow().get().clone())
+}
+
+// Start opening approval requests daily, assigned to the given manager
+#[ic_cdk::update]
+fn start_auto_approval(manager_wallet: String) -> Result<(), Error> {
+    ensure_admin()?;
+    validate_manager_wallet(&manager_wallet)?;
+
+    AUTO_APPROVAL_CONFIG
+        .with(|cell| {
+            cell.borrow_mut().set(AutoApprovalConfig {
+                enabled: true,
+                manager_wallet,
+            })
+        })
+        .expect("Cannot set the auto approval config");
+    schedule_auto_approval();
+    Ok(())
+}
+
+// Stop the daily approval request job
+#[ic_cdk::update]
+fn stop_auto_approval() -> Result<(), Error> {
+    ensure_admin()?;
+
+    let mut config = get_auto_approval_config();
+    config.enabled = false;
+    AUTO_APPROVAL_CONFIG
+        .with(|cell| cell.borrow_mut().set(config))
+        .expect("Cannot set the auto approval config");
+    if let Some(timer_id) = AUTO_APPROVAL_TIMER.with(|timer| timer.borrow_mut().take()) {
+        ic_cdk_timers::clear_timer(timer_id);
+    }
+    Ok(())
+}
+
+// List the pending approvals assigned to a manager
+#[ic_cdk::query]
+fn list_pending_approvals(manager_wallet: String) -> Result<Vec<PayrollApproval>, Error> {
+    let manager = validate_wallet(&manager_wallet)?;
+
+    Ok(APPROVAL_STORAGE.with(|storage| {
+        storage
+            .borrow()
+            .iter()
+            .map(|(_, approval)| approval)
+            .filter(|approval| {
+                approval.status == ApprovalStatus::Pending
+                    && validate_wallet(&approval.manager_wallet).ok() == Some(manager)
+            })
+            .collect()
+    }))
+}
+
+// Retrieve the approval for an employee's working day
+#[ic_cdk::query]
+fn get_approval(nip: u64, date: u64) -> Result<PayrollApproval, Error> {
+    APPROVAL_STORAGE.with(|storage| storage.borrow().get(&(nip, date))).ok_or_else(|| Error::NotFound {
+        msg: format!("Approval request for NIP={} on date {} not found", nip, date),
+    })
+}
+
+// List an employee's pending approvals, oldest date first
+#[ic_cdk::query]
+fn my_pending_approvals(nip: u64) -> Vec<PayrollApproval> {
+    APPROVAL_STORAGE.with(|storage| {
+        storage
+            .borrow()
+            .range((nip, 0)..=(nip, u64::MAX))
+            .map(|(_, approval)| approval)
+            .filter(|approval| approval.status == ApprovalStatus::Pending)
+            .collect()
+    })
+}
+
+// Count a day's approvals as (pending, approved, rejected); paid approvals are not included
+#[ic_cdk::query]
+fn approval_counts_for_date(date: u64) -> (u64, u64, u64) {
+    APPROVAL_STORAGE.with(|storage| {
+        storage
+            .borrow()
+            .iter()
+            .filter(|((_, day), _)| *day == date)
+            .fold((0, 0, 0), |(pending, approved, rejected), (_, approval)| match approval.status {
+                ApprovalStatus::Pending => (pending + 1, approved, rejected),
+                ApprovalStatus::Approved => (pending, approved + 1, rejected),
+                ApprovalStatus::Rejected => (pending, approved, rejected + 1),
+                ApprovalStatus::Paid { .. } => (pending, approved, rejected),
+            })
+    })
+}
+
+// Count pending approvals across all managers without collecting them
+#[ic_cdk::query]
+fn pending_approvals_count() -> u64 {
+    APPROVAL_STORAGE.with(|storage| {
+        storage
+            .borrow()
+            .iter()
+            .filter(|(_, approval)| approval.status == ApprovalStatus::Pending)
+            .count() as u64
+    })
+}
+
+// List approvals one page at a time, optionally only those in a given status.
+// A Paid filter matches every paid approval regardless of its block index.
+#[ic_cdk::query]
+fn list_approvals(status_filter: Option<ApprovalStatus>, offset: u64, limit: u64) -> Vec<PayrollApproval> {
+    let limit = limit.min(MAX_PAGE_SIZE) as usize;
+    APPROVAL_STORAGE.with(|storage| {
+        storage
+            .borrow()
+            .iter()
+            .map(|(_, approval)| approval)
+            .filter(|approval| match &status_filter {
+                Some(status) => discriminant(status) == discriminant(&approval.status),
+                None => true,
+            })
+            .skip(usize::try_from(offset).unwrap_or(usize::MAX))
+            .take(limit)
+            .collect()
+    })
+}
+
+// Approve a pending payroll request.
+// The caller must be an allowlisted manager or the manager_wallet recorded in request_approval.
+#[ic_cdk::update]
+fn approve_payroll(nip: u64, date: u64) -> Result<PayrollApproval, Error> {
+    decide_payroll(nip, date, ApprovalStatus::Approved, None)
+}
+
+// Reject a pending payroll request with a non-empty reason.
+// The caller must be an allowlisted manager or the manager_wallet recorded in request_approval.
+#[ic_cdk::update]
+fn reject_payroll(nip: u64, date: u64, reason: String) -> Result<PayrollApproval, Error> {
+    let reason = reason.trim().to_string();
+    if reason.is_empty() {
+        return Err(Error::InvalidInput {
+            msg: "A reason is required when rejecting payroll".to_string(),
+        });
+    }
+    if reason.chars().count() > MAX_REASON_LENGTH {
+        return Err(Error::InvalidInput {
+            msg: format!("Reason cannot be longer than {} characters", MAX_REASON_LENGTH),
+        });
+    }
+    decide_payroll(nip, date, ApprovalStatus::Rejected, Some(reason))
+}
+
+// Move a pending approval to Approved or Rejected on behalf of an authorized manager
+fn decide_payroll(
+    nip: u64,
+    date: u64,
+    status: ApprovalStatus,
+    reason: Option<String>,
+) -> Result<PayrollApproval, Error> {
+    APPROVAL_STORAGE.with(|storage| {
+        let mut storage = storage.borrow_mut();
+        let mut approval = storage.get(&(nip, date)).ok_or_else(|| Error::NotFound {
+            msg: format!("Approval request for NIP={} on date {} not found", nip, date),
+        })?;
+
+        ensure_caller_can_approve(&approval.manager_wallet)?;
+
+        if approval.status != ApprovalStatus::Pending {
+            return Err(Error::InvalidInput {
+                msg: "Only pending approvals can be approved or rejected".to_string(),
+            });
+        }
+
+        approval.status = status;
+        approval.reason = reason;
+        ensure_fits_storage(&approval)?;
+        log_approval_event(nip, date, Some(ApprovalStatus::Pending), approval.status.clone());
+
+        storage.insert((nip, date), approval.clone());
+        Ok(approval)
+    })
+}
+
+// Approve every pending payroll assigned to the calling manager, returning how many were approved
+#[ic_cdk::update]
+fn approve_all_pending(manager_wallet: String) -> Result<u32, Error> {
+    let manager = validate_wallet(&manager_wallet)?;
+    ensure_caller_is(&manager_wallet)?;
+
+    APPROVAL_STORAGE.with(|storage| {
+        let keys: Vec<(u64, u64)> = storage
+            .borrow()
+            .iter()
+            .filter(|(_, approval)| {
+                approval.status == ApprovalStatus::Pending
+                    && validate_wallet(&approval.manager_wallet).ok() == Some(manager)
+            })
+            .map(|(key, _)| key)
+            .collect();
+
+        let mut storage = storage.borrow_mut();
+        for key in &keys {
+            if let Some(mut approval) = storage.get(key) {
+                approval.status = ApprovalStatus::Approved;
+                storage.insert(*key, approval);
+                log_approval_event(key.0, key.1, Some(ApprovalStatus::Pending), ApprovalStatus::Approved);
+            }
+        }
+        Ok(keys.len() as u32)
+    })
+}
+
+// Withdraw an approval request before it is decided.
+// Only the manager recorded on the request may cancel it.
+#[ic_cdk::update]
+fn cancel_approval_request(nip: u64, date: u64) -> Result<(), Error> {
+    APPROVAL_STORAGE.with(|storage| {
+        let mut storage = storage.borrow_mut();
+        let approval = storage.get(&(nip, date)).ok_or_else(|| Error::NotFound {
+            msg: format!("Approval request for NIP={} on date {} not found", nip, date),
+        })?;
+
+        ensure_caller_is(&approval.manager_wallet)?;
+
+        if approval.status != ApprovalStatus::Pending {
+            return Err(Error::InvalidInput {
+                msg: "Only pending approval requests can be cancelled".to_string(),
+            });
+        }
+
+        storage.remove(&(nip, date));
+        log_approval_removal(nip, date, approval.status);
+        Ok(())
+    })
+}
+
+// Send a rejected approval back for re-review.
+// Only the manager recorded on the request may reopen it.
+#[ic_cdk::update]
+fn reopen_approval(nip: u64, date: u64) -> Result<PayrollApproval, Error> {
+    APPROVAL_STORAGE.with(|storage| {
+        let mut storage = storage.borrow_mut();
+        let mut approval = storage.get(&(nip, date)).ok_or_else(|| Error::NotFound {
+            msg: format!("Approval request for NIP={} on date {} not found", nip, date),
+        })?;
+
+        ensure_caller_is(&approval.manager_wallet)?;
+
+        if approval.status != ApprovalStatus::Rejected {
+            return Err(Error::InvalidInput {
+                msg: "Only rejected approvals can be reopened".to_string(),
+            });
+        }
+
+        approval.status = ApprovalStatus::Pending;
+        approval.reason = None;
+        log_approval_event(nip, date, Some(ApprovalStatus::Rejected), ApprovalStatus::Pending);
+
+        storage.insert((nip, date), approval.clone());
+        Ok(approval)
+    })
+}
+
+// Append an approval status transition to the audit log
+fn log_approval_event(nip: u64, date: u64, old_status: Option<ApprovalStatus>, new_status: ApprovalStatus) {
+    match new_status {
+        ApprovalStatus::Approved => publish_event(EventType::ApprovalApproved, nip),
+        ApprovalStatus::Rejected => publish_event(EventType::ApprovalRejected, nip),
+        ApprovalStatus::Paid { .. } => publish_event(EventType::WagePaid, nip),
+        ApprovalStatus::Pending => {}
+    }
+    append_approval_event(nip, date, old_status, Some(new_status));
+}
+
+// Append the removal of an approval to the audit log
+fn log_approval_removal(nip: u64, date: u64, old_status: ApprovalStatus) {
+    append_approval_event(nip, date, Some(old_status), None);
+}
+
+// Write an entry to the audit log on behalf of the caller
+fn append_approval_event(nip: u64, date: u64, old_status: Option<ApprovalStatus>, new_status: Option<ApprovalStatus>) {
+    APPROVAL_LOG.with(|log| {
+        let mut log = log.borrow_mut();
+        let id = log.last_key_value().map_or(0, |(id, _)| id + 1);
+        log.insert(
+            id,
+            ApprovalEvent {
+                employee_nip: nip,
+                attendance_date: date,
+                old_status,
+                new_status,
+                actor: ic_cdk::caller().to_text(),
+                timestamp: time(),
+            },
+        );
+    });
+}
+
+// Append an event to the polling feed
+fn publish_event(event_type: EventType, nip: u64) {
+    EVENT_LOG.with(|log| {
+        let mut log = log.borrow_mut();
+        let seq = log.last_key_value().map_or(1, |(seq, _)| seq + 1);
+        log.insert(
+            seq,
+            Event {
+                seq,
+                event_type,
+                nip,
+                timestamp: time(),
+            },
+        );
+    });
+}
+
+// Retrieve events published after since_seq, oldest first; pass 0 to start from the beginning
+#[ic_cdk::query]
+fn poll_events(since_seq: u64, limit: u64) -> Vec<Event> {
+    let limit = limit.min(MAX_PAGE_SIZE) as usize;
+    EVENT_LOG.with(|log| {
+        log.borrow()
+            .range(since_seq.saturating_add(1)..)
+            .take(limit)
+            .map(|(_, event)| event)
+            .collect()
+    })
+}
+
+// Retrieve the audit trail of approval changes for an employee, oldest first
+#[ic_cdk::query]
+fn get_approval_log(nip: u64) -> Vec<ApprovalEvent> {
+    APPROVAL_LOG.with(|log| {
+        log.borrow()
+            .iter()
+            .map(|(_, event)| event)
+            .filter(|event| event.employee_nip == nip)
+            .collect()
+    })
+}
+
+// Combine an employee's attendance, approval, and deductions for a day into a payslip
+#[ic_cdk::query]
+fn get_payslip(nip: u64, date: u64) -> Result<Payslip, Error> {
+    let employee = get_employee(nip)?;
+    let attendance = get_attendance(nip, date)?;
+    let approval_status = APPROVAL_STORAGE.with(|storage| storage.borrow().get(&(nip, date)).map(|approval| approval.status));
+    let is_paid = matches!(approval_status, Some(ApprovalStatus::Paid { .. }));
+
+    Ok(Payslip {
+        employee_nip: nip,
+        employee_name: employee.name,
+        date,
+        gross_wage: attendance.daily_wage,
+        net_wage: attendance.net_wage,
+        currency: employee.currency,
+        attendance,
+        approval_status,
+        is_paid,
+    })
+}
+
+// Summarize employees and approvals in a single call
+#[ic_cdk::query]
+fn get_statistics() -> Statistics {
+    let mut statistics = Statistics {
+        employee_count: EMPLOYEE_STORAGE.with(|storage| storage.borrow().len()),
+        ..Default::default()
     };
 
-    EMPLOYEE_STORAGE.with(|storage| storage.borrow_mut().insert(nip, employee.clone()));
-    Ok(employee)
+    APPROVAL_STORAGE.with(|storage| {
+        for (_, approval) in storage.borrow().iter() {
+            match approval.status {
+                ApprovalStatus::Pending => statistics.pending_approvals += 1,
+                ApprovalStatus::Approved => {
+                    statistics.approved_approvals += 1;
+                    statistics.total_approved_wage_amount += approval.wage_amount;
+                }
+                ApprovalStatus::Rejected => statistics.rejected_approvals += 1,
+                ApprovalStatus::Paid { .. } => statistics.paid_approvals += 1,
+            }
+        }
+    });
+
+    statistics
+}
+
+// Sum the wages approved but not yet paid, in minor units.
+// Amounts are added regardless of currency, so mixed-currency payrolls need per-currency care.
+#[ic_cdk::query]
+fn total_unpaid_approved() -> Money {
+    APPROVAL_STORAGE.with(|storage| {
+        storage
+            .borrow()
+            .iter()
+            .filter(|(_, approval)| approval.status == ApprovalStatus::Approved)
+            .map(|(_, approval)| approval.wage_amount)
+            .sum()
+    })
+}
+
+// List approvals still unpaid more than older_than_days after their attendance date, oldest first
+#[ic_cdk::query]
+fn stale_approved(older_than_days: u64) -> Vec<PayrollApproval> {
+    let today = current_day_key();
+    let mut approvals: Vec<PayrollApproval> = APPROVAL_STORAGE.with(|storage| {
+        storage
+            .borrow()
+            .iter()
+            .map(|(_, approval)| approval)
+            .filter(|approval| {
+                approval.status == ApprovalStatus::Approved
+                    && today.saturating_sub(approval.attendance_date) > older_than_days
+            })
+            .collect()
+    });
+    approvals.sort_by_key(|approval| approval.attendance_date);
+    approvals
+}
+
+// List approvals requested between start_ns and end_ns (inclusive), oldest first
+#[ic_cdk::query]
+fn approvals_created_between(start_ns: u64, end_ns: u64) -> Result<Vec<PayrollApproval>, Error> {
+    if start_ns > end_ns {
+        return Err(Error::InvalidInput {
+            msg: "start_ns must not be greater than end_ns".to_string(),
+        });
+    }
+
+    let mut approvals: Vec<PayrollApproval> = APPROVAL_STORAGE.with(|storage| {
+        storage
+            .borrow()
+            .iter()
+            .map(|(_, approval)| approval)
+            .filter(|approval| (start_ns..=end_ns).contains(&approval.created_at))
+            .collect()
+    });
+    approvals.sort_by_key(|approval| approval.created_at);
+    Ok(approvals)
+}
+
+// Report the cycle balance, stable memory size and record counts
+#[ic_cdk::query]
+fn canister_status_summary() -> CanisterStats {
+    CanisterStats {
+        cycle_balance: ic_cdk::api::canister_balance128(),
+        stable_memory_bytes: ic_cdk::api::stable::stable64_size() * WASM_PAGE_SIZE,
+        employee_count: EMPLOYEE_STORAGE.with(|storage| storage.borrow().len()),
+        attendance_count: ATTENDANCE_STORAGE.with(|storage| storage.borrow().len()),
+        approval_count: APPROVAL_STORAGE.with(|storage| storage.borrow().len()),
+    }
+}
+
+// Pay an approved wage to the employee's wallet through the ICP ledger, returning the block index.
+// Only the admin or an allowlisted manager may move the canister's funds.
+#[ic_cdk::update]
+async fn pay_approved_wage(nip: u64, date: u64) -> Result<u64, Error> {
+    ensure_admin_or_manager()?;
+    let approval = get_approval(nip, date)?;
+    ensure_payable(&approval)?;
+
+    let employee = get_employee(nip)?;
+    let recipient = payout_recipient(&employee)?;
+
+    // The status only becomes Paid once the ledger answers, so block concurrent attempts meanwhile
+    if !PAYMENTS_IN_FLIGHT.with(|in_flight| in_flight.borrow_mut().insert((nip, date))) {
+        return Err(Error::InvalidInput {
+            msg: "Payment is already in progress".to_string(),
+        });
+    }
+    let result = pay_in_icp(nip, date, recipient, &approval).await;
+    PAYMENTS_IN_FLIGHT.with(|in_flight| in_flight.borrow_mut().remove(&(nip, date)));
+    let block_index = result?;
+
+    APPROVAL_STORAGE.with(|storage| {
+        let mut storage = storage.borrow_mut();
+        if let Some(mut approval) = storage.get(&(nip, date)) {
+            let old_status = approval.status.clone();
+            approval.status = ApprovalStatus::Paid { block_index };
+            log_approval_event(nip, date, Some(old_status), approval.status.clone());
+            storage.insert((nip, date), approval);
+        }
+    });
+
+    Ok(block_index)
+}
+
+// Pay every approved wage for a day, reporting each employee's block index or failure.
+// Only the admin or an allowlisted manager may move the canister's funds.
+#[ic_cdk::update]
+async fn pay_all_approved(date: u64) -> Result<Vec<(u64, Result<u64, Error>)>, Error> {
+    ensure_admin_or_manager()?;
+    let nips: Vec<u64> = APPROVAL_STORAGE.with(|storage| {
+        storage
+            .borrow()
+            .iter()
+            .filter(|((_, day), approval)| *day == date && approval.status == ApprovalStatus::Approved)
+            .map(|((nip, _), _)| nip)
+            .collect()
+    });
+
+    // Pay one at a time so each transfer sees the statuses left by the previous one
+    let mut results = Vec::with_capacity(nips.len());
+    for nip in nips {
+        results.push((nip, pay_approved_wage(nip, date).await));
+    }
+    Ok(results)
+}
+
+// Work out where an employee's wages are sent from their stored wallet
+fn payout_recipient(employee: &Employee) -> Result<Recipient, Error> {
+    let (kind, wallet) = validate_payout_wallet(&employee.wallet_address)?;
+    Ok(match kind {
+        WalletKind::Principal => Recipient::Principal(validate_wallet(&wallet)?),
+        WalletKind::AccountIdentifier => {
+            Recipient::AccountIdentifier(decode_hex(&wallet).expect("Validated account identifier is not hex"))
+        }
+    })
+}
+
+// Ensure an approval is approved and not yet paid
+fn ensure_payable(approval: &PayrollApproval) -> Result<(), Error> {
+    if approval.status != ApprovalStatus::Approved {
+        return Err(Error::InvalidInput {
+            msg: "Only approved payroll can be paid".to_string(),
+        });
+    }
+    Ok(())
+}
+
+// Transfer an approved wage in ICP, returning the block index.
+// The first attempt fixes the amount and creation time on the approval; a retry after an
+// unclear ledger response resends the same transfer, which the ledger reports as a duplicate.
+// A transfer the ledger definitely refused is dropped, so the next attempt prices a fresh one.
+// Once the ledger's deduplication window has passed, retries fail as too old and need manual review.
+async fn pay_in_icp(nip: u64, date: u64, recipient: Recipient, approval: &PayrollApproval) -> Result<u64, Error> {
+    let payment = match &approval.payment {
+        Some(payment) => payment.clone(),
+        None => {
+            let rate = icp_rate(&approval.currency).await?;
+            let payment = PendingPayment {
+                amount_e8s: to_e8s(to_major_units(approval.wage_amount) / rate),
+                created_at_time: time(),
+            };
+            APPROVAL_STORAGE.with(|storage| {
+                let mut storage = storage.borrow_mut();
+                if let Some(mut approval) = storage.get(&(nip, date)) {
+                    approval.payment = Some(payment.clone());
+                    storage.insert((nip, date), approval);
+                }
+            });
+            payment
+        }
+    };
+
+    let memo = payment_memo(nip, date);
+    let result = match recipient {
+        Recipient::Principal(owner) => transfer_icp(owner, &payment, memo).await,
+        Recipient::AccountIdentifier(account) => transfer_icp_to_account(account, &payment, memo).await,
+    };
+    result.map_err(|failure| settle_failed_transfer(nip, date, failure))
+}
+
+// Forget the pending transfer of an approval the ledger definitely did not execute
+fn settle_failed_transfer(nip: u64, date: u64, failure: TransferFailure) -> Error {
+    match failure {
+        TransferFailure::Rejected(error) => {
+            APPROVAL_STORAGE.with(|storage| {
+                let mut storage = storage.borrow_mut();
+                if let Some(mut approval) = storage.get(&(nip, date)) {
+                    approval.payment = None;
+                    storage.insert((nip, date), approval);
+                }
+            });
+            error
+        }
+        TransferFailure::Unknown(error) => error,
+    }
+}
+
+// Ledger memo identifying the wage payment for an employee's working day
+fn payment_memo(nip: u64, date: u64) -> u64 {
+    let digest = Sha256::new()
+        .chain_update(nip.to_be_bytes())
+        .chain_update(date.to_be_bytes())
+        .finalize();
+    u64::from_be_bytes(digest[..8].try_into().expect("SHA-256 digest is 32 bytes"))
+}
+
+// Convert an ICP amount into e8s, rounding half up to the nearest e8.
+// Works on the amount's decimal digits rather than multiplying floats, so 1.23456789 ICP is
+// exactly 123_456_789 e8s.
+fn to_e8s(amount: f64) -> u64 {
+    if !amount.is_finite() || amount <= 0.0 {
+        return 0;
+    }
+    // Display prints the shortest digits that round-trip and never uses an exponent
+    let decimal = amount.to_string();
+    let (whole, fraction) = decimal.split_once('.').unwrap_or((&decimal, ""));
+    let whole: u64 = whole.parse().unwrap_or(u64::MAX);
+    let digits = fraction.as_bytes();
+    let fraction_e8s = (0..E8S_DECIMAL_PLACES).fold(0, |e8s, i| {
+        e8s * 10 + digits.get(i).map_or(0, |digit| u64::from(digit - b'0'))
+    });
+    let round_up = digits.get(E8S_DECIMAL_PLACES).is_some_and(|digit| *digit >= b'5');
+    whole
+        .saturating_mul(E8S_PER_ICP)
+        .saturating_add(fraction_e8s)
+        .saturating_add(u64::from(round_up))
+}
+
+// Value of one ICP in the currency, from the cache if fresh or the price API otherwise
+async fn icp_rate(currency: &str) -> Result<f64, Error> {
+    let cached = EXCHANGE_RATES.with(|cell| {
+        cell.borrow()
+            .get()
+            .rates
+            .iter()
+            .find(|rate| rate.currency == currency && time().saturating_sub(rate.fetched_at) < RATE_MAX_AGE_NANOS)
+            .map(|rate| rate.rate)
+    });
+    match cached {
+        Some(rate) => Ok(rate),
+        None => request_icp_rate(currency).await,
+    }
+}
+
+// Fetch the value of one ICP in a currency from the price API and cache it
+#[ic_cdk::update]
+async fn fetch_icp_rate(currency: String) -> Result<f64, Error> {
+    ensure_admin()?;
+    let currency = validate_currency(&currency)?;
+    request_icp_rate(&currency).await
+}
+
+// Query the price API through an HTTPS outcall and cache the resulting rate
+async fn request_icp_rate(currency: &str) -> Result<f64, Error> {
+    let url = PRICE_API_URL.with(|cell| cell.borrow().get().replace("{currency}", currency));
+    let request = CanisterHttpRequestArgument {
+        url,
+        max_response_bytes: Some(MAX_PRICE_RESPONSE_BYTES),
+        method: HttpMethod::GET,
+        headers: Vec::new(),
+        body: None,
+        transform: Some(TransformContext::from_name("transform_rate_response".to_string(), Vec::new())),
+    };
+
+    let (response,) = http_request(request, HTTP_OUTCALL_CYCLES)
+        .await
+        .map_err(|(code, msg)| Error::RateUnavailable {
+            msg: format!("Price API call failed ({:?}): {}", code, msg),
+        })?;
+    if response.status != 200 {
+        return Err(Error::RateUnavailable {
+            msg: format!("Price API answered with status {}", response.status),
+        });
+    }
+
+    let rate = parse_rate(&response.body).ok_or_else(|| Error::RateUnavailable {
+        msg: "Price API response did not contain a valid rate".to_string(),
+    })?;
+
+    EXCHANGE_RATES.with(|cell| {
+        let mut cache = cell.borrow().get().clone();
+        cache.rates.retain(|cached| cached.currency != currency);
+        cache.rates.push(ExchangeRate {
+            currency: currency.to_string(),
+            rate,
+            fetched_at: time(),
+        });
+        cell.borrow_mut().set(cache).expect("Cannot cache the exchange rate");
+    });
+
+    Ok(rate)
+}
+
+// Read a positive rate from a price API body shaped {"data": {"amount": "12.34", ...}}
+fn parse_rate(body: &[u8]) -> Option<f64> {
+    serde_json::from_slice::<serde_json::Value>(body)
+        .ok()
+        .and_then(|body| body["data"]["amount"].as_str().and_then(|amount| amount.parse::<f64>().ok()))
+        .filter(|rate| rate.is_finite() && *rate > 0.0)
+}
+
+// Reduce price API responses to the rate at a fixed precision so every replica sees the same
+// response; timestamps and other fields in the body would otherwise break consensus
+#[ic_cdk::query]
+fn transform_rate_response(args: TransformArgs) -> HttpResponse {
+    let body = match parse_rate(&args.response.body) {
+        Some(rate) => serde_json::json!({ "data": { "amount": format!("{:.*}", RATE_DECIMAL_PLACES, rate) } })
+            .to_string()
+            .into_bytes(),
+        None => Vec::new(),
+    };
+    HttpResponse {
+        status: args.response.status,
+        headers: Vec::new(),
+        body,
+    }
+}
+
+// Transfer ICP from this canister to the given principal, returning the block index.
+// A duplicate of an earlier transfer returns that transfer's block index.
+async fn transfer_icp(recipient: Principal, payment: &PendingPayment, memo: u64) -> Result<u64, TransferFailure> {
+    let ledger = Principal::from_text(ICP_LEDGER_CANISTER_ID).expect("Invalid ledger canister id");
+    let arg = TransferArg {
+        from_subaccount: None,
+        to: Account {
+            owner: recipient,
+            subaccount: None,
+        },
+        amount: Nat::from(payment.amount_e8s),
+        fee: None,
+        memo: Some(memo.to_be_bytes().to_vec()),
+        created_at_time: Some(payment.created_at_time),
+    };
+
+    let (result,): (Result<Nat, TransferError>,) = ic_cdk::call(ledger, "icrc1_transfer", (arg,))
+        .await
+        .map_err(|(code, msg)| {
+            TransferFailure::Unknown(Error::PaymentFailed {
+                msg: format!("Ledger call failed ({:?}): {}", code, msg),
+            })
+        })?;
+    let block_index = match result {
+        Ok(block_index) | Err(TransferError::Duplicate { duplicate_of: block_index }) => block_index,
+        Err(err) => return Err(classify_transfer_error(err)),
+    };
+
+    u64::try_from(&block_index.0).map_err(|_| {
+        TransferFailure::Unknown(Error::PaymentFailed {
+            msg: "Ledger block index does not fit in u64".to_string(),
+        })
+    })
+}
+
+// Sort an icrc1_transfer error by whether the ledger definitely did not execute the transfer.
+// TooOld is not definite: an earlier attempt with the same creation time may have gone through.
+fn classify_transfer_error(err: TransferError) -> TransferFailure {
+    let error = Error::PaymentFailed {
+        msg: format!("Ledger rejected the transfer: {:?}", err),
+    };
+    match err {
+        TransferError::BadFee { .. }
+        | TransferError::BadBurn { .. }
+        | TransferError::InsufficientFunds { .. }
+        | TransferError::CreatedInFuture { .. } => TransferFailure::Rejected(error),
+        _ => TransferFailure::Unknown(error),
+    }
+}
+
+// Transfer ICP to a ledger account identifier through the legacy transfer endpoint.
+// A duplicate of an earlier transfer returns that transfer's block index.
+async fn transfer_icp_to_account(account: Vec<u8>, payment: &PendingPayment, memo: u64) -> Result<u64, TransferFailure> {
+    let ledger = Principal::from_text(ICP_LEDGER_CANISTER_ID).expect("Invalid ledger canister id");
+    let args = LegacyTransferArgs {
+        memo,
+        amount: Tokens {
+            e8s: payment.amount_e8s,
+        },
+        fee: Tokens {
+            e8s: LEDGER_TRANSFER_FEE_E8S,
+        },
+        from_subaccount: None,
+        to: account,
+        created_at_time: Some(TimeStamp {
+            timestamp_nanos: payment.created_at_time,
+        }),
+    };
+
+    let (result,): (Result<u64, LegacyTransferError>,) = ic_cdk::call(ledger, "transfer", (args,))
+        .await
+        .map_err(|(code, msg)| {
+            TransferFailure::Unknown(Error::PaymentFailed {
+                msg: format!("Ledger call failed ({:?}): {}", code, msg),
+            })
+        })?;
+    match result {
+        Ok(block_index) | Err(LegacyTransferError::TxDuplicate { duplicate_of: block_index }) => Ok(block_index),
+        Err(err) => {
+            let error = Error::PaymentFailed {
+                msg: format!("Ledger rejected the transfer: {:?}", err),
+            };
+            Err(match err {
+                LegacyTransferError::BadFee { .. }
+                | LegacyTransferError::InsufficientFunds { .. }
+                | LegacyTransferError::TxCreatedInFuture => TransferFailure::Rejected(error),
+                _ => TransferFailure::Unknown(error),
+            })
+        }
+    }
+}
+
+// Validate an employee wallet, which may be a principal or a ledger account identifier.
+// Returns the kind together with the canonical form: principal text or lowercase hex.
+fn validate_payout_wallet(wallet_address: &str) -> Result<(WalletKind, String), Error> {
+    let wallet_address = wallet_address.trim();
+    if let Ok(principal) = Principal::from_text(wallet_address) {
+        return Ok((WalletKind::Principal, principal.to_text()));
+    }
+
+    // An account identifier is a big-endian CRC32 of the 28-byte hash followed by the hash
+    let account = decode_hex(wallet_address)
+        .filter(|bytes| bytes.len() == 32)
+        .ok_or_else(|| Error::InvalidWallet {
+            msg: "Wallet must be a principal or a 64-character account identifier".to_string(),
+        })?;
+    if account[..4] != crc32(&account[4..]).to_be_bytes() {
+        return Err(Error::InvalidWallet {
+            msg: "Account identifier checksum does not match".to_string(),
+        });
+    }
+    Ok((WalletKind::AccountIdentifier, encode_hex(&account)))
+}
+
+// Decode a hex string into bytes, or None if it is not valid hex
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    // from_str_radix alone would accept a sign, as in "+f"
+    if !hex.len().is_multiple_of(2) || !hex.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok())
+        .collect()
+}
+
+// Encode bytes as lowercase hex, the canonical form of an account identifier
+fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+// CRC-32 (IEEE) checksum, as used by ledger account identifiers
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = u32::MAX;
+    for byte in bytes {
+        crc ^= u32::from(*byte);
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+// Validate wallet address format
+fn validate_wallet(wallet_address: &str) -> Result<Principal, Error> {
+    Principal::from_text(wallet_address).map_err(|_| Error::InvalidWallet {
+        msg: "Invalid wallet address format".to_string(),
+    })
+}
+
+// Ensure the caller is the principal behind the given wallet address
+fn ensure_caller_is(wallet_address: &str) -> Result<(), Error> {
+    if ic_cdk::caller() != validate_wallet(wallet_address)? {
+        return Err(Error::Unauthorized {
+            msg: "Caller is not the assigned manager".to_string(),
+        });
+    }
+    Ok(())
+}
+
+// Ensure the caller is an allowlisted manager or the principal behind the given wallet address
+fn ensure_caller_can_approve(wallet_address: &str) -> Result<(), Error> {
+    let caller = principal_key(&ic_cdk::caller());
+    if MANAGERS.with(|managers| managers.borrow().contains_key(&caller)) {
+        return Ok(());
+    }
+    ensure_caller_is(wallet_address)
+}
+
+// Validate a wallet assigned to approve payroll: it must be an allowlisted or the default manager
+fn validate_manager_wallet(wallet_address: &str) -> Result<Principal, Error> {
+    let manager = validate_wallet(wallet_address)?;
+    let allowlisted = MANAGERS.with(|managers| managers.borrow().contains_key(&principal_key(&manager)));
+    if !allowlisted && get_default_manager() != Some(manager) {
+        return Err(Error::InvalidInput {
+            msg: "Manager wallet is neither an allowlisted manager nor the default manager".to_string(),
+        });
+    }
+    Ok(manager)
+}
+
+// Storage key for a principal; principals are at most 29 bytes long
+fn principal_key(principal: &Principal) -> Blob<29> {
+    Blob::try_from(principal.as_slice()).expect("Principal is longer than 29 bytes")
+}
+
+// Report the caller's highest role: admin, allowlisted manager, employee wallet owner, or none
+#[ic_cdk::query]
+fn my_role() -> Role {
+    if ensure_admin().is_ok() {
+        return Role::Admin;
+    }
+    let caller = ic_cdk::caller();
+    if MANAGERS.with(|managers| managers.borrow().contains_key(&principal_key(&caller))) {
+        return Role::Manager;
+    }
+    let is_employee = EMPLOYEE_STORAGE.with(|storage| {
+        storage
+            .borrow()
+            .iter()
+            .any(|(_, employee)| validate_wallet(&employee.wallet_address).ok() == Some(caller))
+    });
+    if is_employee {
+        return Role::Employee;
+    }
+    Role::Unknown
+}
+
+// Ensure the caller is the admin principal set at init
+fn ensure_admin() -> Result<(), Error> {
+    let admin = ADMIN.with(|cell| cell.borrow().get().clone());
+    if admin.is_empty() || ic_cdk::caller().as_slice() != admin.as_slice() {
+        return Err(Error::Unauthorized {
+            msg: "Caller is not the admin".to_string(),
+        });
+    }
+    Ok(())
+}
+
+// Ensure the caller is the admin or an allowlisted manager
+fn ensure_admin_or_manager() -> Result<(), Error> {
+    let caller = principal_key(&ic_cdk::caller());
+    if ensure_admin().is_ok() || MANAGERS.with(|managers| managers.borrow().contains_key(&caller)) {
+        return Ok(());
+    }
+    Err(Error::Unauthorized {
+        msg: "Caller is not the admin or an allowlisted manager".to_string(),
+    })
+}
+
+// Ensure the caller is the admin or the principal behind the employee's wallet
+fn ensure_admin_or_employee(employee: &Employee) -> Result<(), Error> {
+    if ensure_admin().is_ok() || validate_wallet(&employee.wallet_address).ok() == Some(ic_cdk::caller()) {
+        return Ok(());
+    }
+    Err(Error::Unauthorized {
+        msg: "Caller is not the admin or the employee".to_string(),
+    })
+}
+
+// Ensure the caller is the principal behind the employee's wallet
+fn ensure_caller_is_employee(employee: &Employee) -> Result<(), Error> {
+    if validate_wallet(&employee.wallet_address).ok() != Some(ic_cdk::caller()) {
+        return Err(Error::Unauthorized {
+            msg: "Caller is not the employee".to_string(),
+        });
+    }
+    Ok(())
+}
+
+// Retrieve the admin principal
+#[ic_cdk::query]
+fn get_admin() -> Principal {
+    ADMIN.with(|cell| Principal::from_slice(cell.borrow().get()))
+}
+
+// Hand the admin role over to another principal
+#[ic_cdk::update]
+fn set_admin(new_admin: Principal) -> Result<(), Error> {
+    ensure_admin()?;
+    ADMIN
+        .with(|cell| cell.borrow_mut().set(new_admin.as_slice().to_vec()))
+        .expect("Cannot set the admin");
+    Ok(())
 }
 
-// Retrieve employee details by NIP
+// Retrieve the default manager, if one is configured
 #[ic_cdk::query]
-fn get_employee(nip: u64) -> Result<Employee, Error> {
-    EMPLOYEE_STORAGE.with(|storage| {
-        storage.borrow().get(&nip).ok_or(Error::NotFound {
-            msg: format!("Employee with NIP={} not found", nip),
-        })
+fn get_default_manager() -> Option<Principal> {
+    DEFAULT_MANAGER.with(|cell| {
+        let bytes = cell.borrow().get().clone();
+        (!bytes.is_empty()).then(|| Principal::from_slice(&bytes))
     })
 }
 
-// Record attendance for an employee
+// Change the default manager used by request_approval_default; None clears it
 #[ic_cdk::update]
-fn record_attendance(nip: u64, check_in: u64, check_out: u64) -> Result<Attendance, Error> {
-    // Validate employee existence
-    let employee = EMPLOYEE_STORAGE.with(|storage| storage.borrow().get(&nip)).ok_or_else(|| Error::NotFound {
-        msg: format!("Employee with NIP={} not found", nip),
-    })?;
+fn set_default_manager(manager: Option<Principal>) -> Result<(), Error> {
+    ensure_admin()?;
+    let bytes = manager.map(|manager| manager.as_slice().to_vec()).unwrap_or_default();
+    DEFAULT_MANAGER
+        .with(|cell| cell.borrow_mut().set(bytes))
+        .expect("Cannot set the default manager");
+    Ok(())
+}
 
-    let total_hours = calculate_work_hours(check_in, check_out);
-    if total_hours <= 0.0 {
+// Allow a manager to approve any payroll
+#[ic_cdk::update]
+fn add_manager(manager: Principal) -> Result<(), Error> {
+    ensure_admin()?;
+    MANAGERS.with(|managers| managers.borrow_mut().insert(principal_key(&manager), ()));
+    Ok(())
+}
+
+// Remove a manager from the approver allowlist
+#[ic_cdk::update]
+fn remove_manager(manager: Principal) -> Result<(), Error> {
+    ensure_admin()?;
+    MANAGERS
+        .with(|managers| managers.borrow_mut().remove(&principal_key(&manager)))
+        .ok_or_else(|| Error::NotFound {
+            msg: format!("Manager {} not found", manager),
+        })
+}
+
+// List the managers allowed to approve any payroll
+#[ic_cdk::query]
+fn list_managers() -> Vec<Principal> {
+    MANAGERS.with(|managers| {
+        managers
+            .borrow()
+            .iter()
+            .map(|(key, _)| Principal::from_slice(key.as_slice()))
+            .collect()
+    })
+}
+
+// List every distinct manager_wallet named on an approval request, allowlisted or not
+#[ic_cdk::query]
+fn list_manager_wallets() -> Vec<String> {
+    let wallets: BTreeSet<String> = APPROVAL_STORAGE.with(|storage| {
+        storage
+            .borrow()
+            .iter()
+            .map(|(_, approval)| approval.manager_wallet)
+            .collect()
+    });
+    wallets.into_iter().collect()
+}
+
+// Retrieve the configured retirement age
+#[ic_cdk::query]
+fn get_retirement_age() -> u32 {
+    RETIREMENT_AGE.with(|cell| *cell.borrow().get())
+}
+
+// Change the retirement age used for pension calculations and update every employee's pension figures
+#[ic_cdk::update]
+fn set_retirement_age(age: u32) -> Result<(), Error> {
+    ensure_admin()?;
+    if !(MIN_RETIREMENT_AGE..=MAX_RETIREMENT_AGE).contains(&age) {
         return Err(Error::InvalidInput {
-            msg: "Invalid check-in or check-out time".to_string(),
+            msg: format!(
+                "Retirement age must be between {} and {}",
+                MIN_RETIREMENT_AGE, MAX_RETIREMENT_AGE
+            ),
         });
     }
+    RETIREMENT_AGE
+        .with(|cell| cell.borrow_mut().set(age))
+        .expect("Cannot set the retirement age");
 
-    let daily_wage = calculate_daily_wage(total_hours, employee.wage_per_hour);
+    // Employees store their pension figures, so bring every record in line with the new age
+    EMPLOYEE_STORAGE.with(|storage| {
+        let employees: Vec<(u64, Employee)> = storage.borrow().iter().collect();
+        let mut storage = storage.borrow_mut();
+        for (nip, mut employee) in employees {
+            employee.pension_age = calculate_pension_age();
+            employee.years_to_pension = years_until_pension(employee.age);
+            storage.insert(nip, employee);
+        }
+    });
+    certify_employees();
+    Ok(())
+}
 
-    let attendance = Attendance {
-        check_in,
-        check_out,
-        total_hours,
-        daily_wage,
+// Retrieve the hourly wage floor and ceiling, in minor units
+#[ic_cdk::query]
+fn get_wage_bounds() -> WageBounds {
+    WAGE_BOUNDS.with(|cell| cell.borrow().get().clone())
+}
+
+// Change the hourly wage floor and ceiling, in major units; None removes the ceiling.
+// Existing employees keep their wages and are checked on their next update.
+#[ic_cdk::update]
+fn set_wage_bounds(min_wage_per_hour: f64, max_wage_per_hour: Option<f64>) -> Result<(), Error> {
+    ensure_admin()?;
+    validate_amount("min_wage_per_hour", min_wage_per_hour, MAX_WAGE_PER_HOUR)?;
+    if let Some(max_wage_per_hour) = max_wage_per_hour {
+        validate_amount("max_wage_per_hour", max_wage_per_hour, MAX_WAGE_PER_HOUR)?;
+    }
+    let bounds = WageBounds {
+        min_wage_per_hour: to_minor_units(min_wage_per_hour),
+        max_wage_per_hour: max_wage_per_hour.map(to_minor_units),
     };
+    if let Some(max) = bounds.max_wage_per_hour {
+        if max < bounds.min_wage_per_hour {
+            return Err(Error::InvalidInput {
+                msg: "Maximum wage cannot be below the minimum wage".to_string(),
+            });
+        }
+    }
+    WAGE_BOUNDS
+        .with(|cell| cell.borrow_mut().set(bounds))
+        .expect("Cannot set the wage bounds");
+    Ok(())
+}
 
-    let current_date = time() / (24 * 60 * 60 * 1_000_000_000);
-    ATTENDANCE_STORAGE.with(|storage| {
-        storage
-            .borrow_mut()
-            .insert((nip, current_date), attendance.clone())
-    });
+// Retrieve the days of the week that count as workdays, 0 = Monday through 6 = Sunday
+#[ic_cdk::query]
+fn get_workweek() -> Vec<u8> {
+    WORKWEEK.with(|cell| cell.borrow().get().clone())
+}
 
-    Ok(attendance)
+// Change the days of the week that count as workdays
+#[ic_cdk::update]
+fn set_workweek(mut days: Vec<u8>) -> Result<(), Error> {
+    ensure_admin()?;
+    if days.is_empty() || days.iter().any(|day| *day > 6) {
+        return Err(Error::InvalidInput {
+            msg: "Workweek must list at least one day between 0 (Monday) and 6 (Sunday)".to_string(),
+        });
+    }
+    days.sort_unstable();
+    days.dedup();
+    WORKWEEK
+        .with(|cell| cell.borrow_mut().set(days))
+        .expect("Cannot set the workweek");
+    Ok(())
+}
+
+// Retrieve the tax and pension rates applied to new attendance
+#[ic_cdk::query]
+fn get_deduction_rates() -> DeductionRates {
+    DEDUCTION_RATES.with(|cell| cell.borrow().get().clone())
+}
+
+// Change the tax and pension rates, each a fraction of the gross wage
+#[ic_cdk::update]
+fn set_deduction_rates(tax_rate: f64, pension_rate: f64) -> Result<(), Error> {
+    ensure_admin()?;
+    if !(0.0..=1.0).contains(&tax_rate) || !(0.0..=1.0).contains(&pension_rate) || tax_rate + pension_rate > 1.0 {
+        return Err(Error::InvalidInput {
+            msg: "Deduction rates must be between 0 and 1 and not exceed 1 combined".to_string(),
+        });
+    }
+    DEDUCTION_RATES
+        .with(|cell| cell.borrow_mut().set(DeductionRates { tax_rate, pension_rate }))
+        .expect("Cannot set the deduction rates");
+    Ok(())
 }
 
-// Request payroll approval for an employee
+// Retrieve the longest shift accepted by record_attendance
+#[ic_cdk::query]
+fn get_max_shift_hours() -> f64 {
+    MAX_SHIFT_HOURS.with(|cell| *cell.borrow().get())
+}
+
+// Change the longest shift accepted by record_attendance
 #[ic_cdk::update]
-fn request_approval(nip: u64, manager_wallet: String) -> Result<PayrollApproval, Error> {
-    // Validate wallet address
-    let _ = validate_wallet(&manager_wallet)?;
+fn set_max_shift_hours(hours: f64) -> Result<(), Error> {
+    ensure_admin()?;
+    if !(hours > 0.0 && hours <= 24.0) {
+        return Err(Error::InvalidInput {
+            msg: "Maximum shift hours must be greater than 0 and at most 24".to_string(),
+        });
+    }
+    MAX_SHIFT_HOURS
+        .with(|cell| cell.borrow_mut().set(hours))
+        .expect("Cannot set the max shift hours");
+    Ok(())
+}
 
-    let current_date = time() / (24 * 60 * 60 * 1_000_000_000);
+// Retrieve the length of the standard working day, in hours
+#[ic_cdk::query]
+fn get_standard_daily_hours() -> f64 {
+    STANDARD_DAILY_HOURS.with(|cell| *cell.borrow().get())
+}
 
-    let attendance = ATTENDANCE_STORAGE.with(|storage| storage.borrow().get(&(nip, current_date))).ok_or_else(|| Error::NotFound {
-        msg: format!("Attendance for NIP={} on current date not found", nip),
-    })?;
+// Change the length of the standard working day used for paid leave and the overtime threshold
+#[ic_cdk::update]
+fn set_standard_daily_hours(hours: f64) -> Result<(), Error> {
+    ensure_admin()?;
+    if !(hours > 0.0 && hours <= 24.0) {
+        return Err(Error::InvalidInput {
+            msg: "Standard daily hours must be greater than 0 and at most 24".to_string(),
+        });
+    }
+    STANDARD_DAILY_HOURS
+        .with(|cell| cell.borrow_mut().set(hours))
+        .expect("Cannot set the standard daily hours");
+    Ok(())
+}
 
-    let approval = PayrollApproval {
-        employee_nip: nip,
-        attendance_date: current_date,
-        wage_amount: attendance.daily_wage,
-        status: ApprovalStatus::Pending,
-        manager_wallet,
-    };
+// Retrieve the scheduled shift start and grace period used to measure late check-ins
+#[ic_cdk::query]
+fn get_shift_schedule() -> ShiftSchedule {
+    SHIFT_SCHEDULE.with(|cell| cell.borrow().get().clone())
+}
 
-    APPROVAL_STORAGE.with(|storage| {
-        storage
-            .borrow_mut()
-            .insert((nip, current_date), approval.clone())
-    });
+// Change the scheduled shift start (minutes after midnight UTC, None to disable) and grace period
+#[ic_cdk::update]
+fn set_shift_schedule(start_minute: Option<u32>, grace_minutes: u32) -> Result<(), Error> {
+    ensure_admin()?;
+    let minutes_per_day = (NANOS_PER_DAY / NANOS_PER_MINUTE) as u32;
+    if start_minute.unwrap_or(0) >= minutes_per_day || grace_minutes >= minutes_per_day {
+        return Err(Error::InvalidInput {
+            msg: format!("Shift start and grace period must be less than {} minutes", minutes_per_day),
+        });
+    }
+    SHIFT_SCHEDULE
+        .with(|cell| {
+            cell.borrow_mut().set(ShiftSchedule {
+                start_minute,
+                grace_minutes,
+            })
+        })
+        .expect("Cannot set the shift schedule");
+    Ok(())
+}
 
-    Ok(approval)
+// Retrieve the number of decimal places allowed in wage_per_hour
+#[ic_cdk::query]
+fn get_wage_decimal_places() -> u32 {
+    WAGE_DECIMAL_PLACES.with(|cell| *cell.borrow().get())
 }
 
-// Approve or reject payroll for an employee
+// Change the number of decimal places allowed in wage_per_hour
 #[ic_cdk::update]
-async fn approve_payroll(nip: u64, date: u64, approved: bool) -> Result<PayrollApproval, Error> {
-    APPROVAL_STORAGE.with(|storage| {
-        let mut storage = storage.borrow_mut();
-        let mut approval = storage.get(&(nip, date)).ok_or_else(|| Error::NotFound {
-            msg: format!("Approval request for NIP={} on date {} not found", nip, date),
-        })?;
+fn set_wage_decimal_places(places: u32) -> Result<(), Error> {
+    ensure_admin()?;
+    if places > MAX_WAGE_DECIMAL_PLACES {
+        return Err(Error::InvalidInput {
+            msg: format!("Wage decimal places cannot exceed {}", MAX_WAGE_DECIMAL_PLACES),
+        });
+    }
+    WAGE_DECIMAL_PLACES
+        .with(|cell| cell.borrow_mut().set(places))
+        .expect("Cannot set the wage decimal places");
+    Ok(())
+}
 
-        if approved {
-            approval.status = ApprovalStatus::Approved;
-        } else {
-            approval.status = ApprovalStatus::Rejected;
-        }
+// Retrieve the most employees the canister stores
+#[ic_cdk::query]
+fn get_max_employees() -> u64 {
+    MAX_EMPLOYEES.with(|cell| *cell.borrow().get())
+}
 
-        storage.insert((nip, date), approval.clone());
-        Ok(approval)
-    })
+// Change the most employees the canister stores; existing employees above a lowered cap are kept
+#[ic_cdk::update]
+fn set_max_employees(limit: u64) -> Result<(), Error> {
+    ensure_admin()?;
+    if limit == 0 {
+        return Err(Error::InvalidInput {
+            msg: "Maximum employees must be greater than 0".to_string(),
+        });
+    }
+    MAX_EMPLOYEES
+        .with(|cell| cell.borrow_mut().set(limit))
+        .expect("Cannot set the max employees");
+    Ok(())
 }
 
-// Validate wallet address format
-fn validate_wallet(wallet_address: &str) -> Result<Principal, Error> {
-    Principal::from_text(wallet_address).map_err(|_| Error::InvalidWallet {
-        msg: "Invalid wallet address format".to_string(),
-    })
+// Retrieve how prorated wages are rounded
+#[ic_cdk::query]
+fn get_rounding_mode() -> RoundingMode {
+    ROUNDING_MODE.with(|cell| *cell.borrow().get())
+}
+
+// Change how prorated wages are rounded for attendance recorded from now on
+#[ic_cdk::update]
+fn set_rounding_mode(mode: RoundingMode) -> Result<(), Error> {
+    ensure_admin()?;
+    ROUNDING_MODE
+        .with(|cell| cell.borrow_mut().set(mode))
+        .expect("Cannot set the rounding mode");
+    Ok(())
+}
+
+// Retrieve the currency assigned to employees added without one
+#[ic_cdk::query]
+fn get_default_currency() -> String {
+    DEFAULT_EMPLOYEE_CURRENCY.with(|cell| cell.borrow().get().clone())
+}
+
+// Change the currency assigned to employees added without one
+#[ic_cdk::update]
+fn set_default_currency(currency: String) -> Result<(), Error> {
+    ensure_admin()?;
+    let currency = validate_currency(&currency)?;
+    DEFAULT_EMPLOYEE_CURRENCY
+        .with(|cell| cell.borrow_mut().set(currency))
+        .expect("Cannot set the default currency");
+    Ok(())
+}
+
+// Retrieve the price API URL template
+#[ic_cdk::query]
+fn get_price_api_url() -> String {
+    PRICE_API_URL.with(|cell| cell.borrow().get().clone())
+}
+
+// Change the price API URL template; {currency} is replaced by the wage currency
+#[ic_cdk::update]
+fn set_price_api_url(url: String) -> Result<(), Error> {
+    ensure_admin()?;
+    if !url.starts_with("https://") {
+        return Err(Error::InvalidInput {
+            msg: "Price API URL must use https".to_string(),
+        });
+    }
+    PRICE_API_URL
+        .with(|cell| cell.borrow_mut().set(url))
+        .expect("Cannot set the price API URL");
+    Ok(())
+}
+
+// Report the deployed build: crate version, build timestamp and optional git hash.
+// The git hash is taken from the GIT_HASH environment variable at build time.
+#[ic_cdk::query]
+fn version() -> String {
+    let mut version = format!(
+        "{} (built at {})",
+        env!("CARGO_PKG_VERSION"),
+        env!("BUILD_TIMESTAMP")
+    );
+    if let Some(git_hash) = option_env!("GIT_HASH") {
+        version.push_str(&format!(", git {}", git_hash));
+    }
+    version
 }
 
 // Export the candid interface
 ic_cdk::export_candid!();
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_employee(nip: u64) -> Employee {
+        Employee {
+            nip,
+            name: "Ana".to_string(),
+            age: 30,
+            pension_age: DEFAULT_RETIREMENT_AGE,
+            years_to_pension: DEFAULT_RETIREMENT_AGE - 30,
+            wage_per_hour: 1_250,
+            currency: DEFAULT_CURRENCY.to_string(),
+            wallet_address: Principal::anonymous().to_text(),
+            is_active: true,
+            created_at: 1,
+            ..Default::default()
+        }
+    }
+
+    fn sample_payload() -> EmployeePayload {
+        EmployeePayload {
+            name: "Ana".to_string(),
+            age: 30,
+            wage_per_hour: 12.5,
+            wallet_address: Principal::anonymous().to_text(),
+            currency: None,
+            employment_type: None,
+            monthly_salary: None,
+        }
+    }
+
+    #[test]
+    fn employee_survives_upgrade() {
+        let memory = DefaultMemoryImpl::default();
+        {
+            let manager = MemoryManager::init(memory.clone());
+            let mut employees: StableBTreeMap<u64, Employee, Memory> = StableBTreeMap::init(manager.get(MemoryId::new(1)));
+            employees.insert(7, sample_employee(7));
+        }
+
+        // An upgrade drops the heap and re-opens every structure on the same stable memory
+        let manager = MemoryManager::init(memory);
+        let employees: StableBTreeMap<u64, Employee, Memory> = StableBTreeMap::init(manager.get(MemoryId::new(1)));
+        let employee = employees.get(&7).expect("employee lost across upgrade");
+        assert_eq!(employee.to_bytes(), sample_employee(7).to_bytes());
+    }
+
+    #[test]
+    fn eight_hour_shift_is_eight_hours() {
+        let check_in = 1_700_000_000 * 1_000_000_000;
+        assert_eq!(calculate_work_hours(check_in, check_in + 8 * NANOS_PER_HOUR), 8.0);
+        assert_eq!(calculate_work_hours(check_in, check_in), 0.0);
+    }
+
+    #[test]
+    fn shift_of_exactly_the_maximum_is_accepted() {
+        let check_in = 1_700_000_000 * 1_000_000_000;
+        let max_nanos = (DEFAULT_MAX_SHIFT_HOURS * NANOS_PER_HOUR as f64) as u64;
+        assert!(validate_shift_length(check_in, check_in + max_nanos).is_ok());
+        assert!(validate_shift_length(check_in, check_in + max_nanos + 1).is_err());
+    }
+
+    #[test]
+    fn thirty_daily_wages_sum_exactly() {
+        let wage_per_hour = to_minor_units(12.34);
+        let daily_wage = calculate_daily_wage(7 * NANOS_PER_HOUR + 30 * NANOS_PER_MINUTE, wage_per_hour);
+        assert_eq!(daily_wage, 9_255);
+        let month: Money = (0..30).map(|_| daily_wage).sum();
+        assert_eq!(month, 277_650);
+    }
+
+    // Shapes written by the first release, when amounts were f64 in major units
+    #[derive(candid::CandidType)]
+    struct FirstReleaseEmployee {
+        nip: u64,
+        name: String,
+        age: u32,
+        pension_age: u32,
+        wage_per_hour: f64,
+        sk_file: Option<PdfFile>,
+        wallet_address: String,
+        created_at: u64,
+        updated_at: Option<u64>,
+    }
+
+    #[derive(candid::CandidType)]
+    struct FirstReleaseAttendance {
+        check_in: u64,
+        check_out: u64,
+        total_hours: f64,
+        daily_wage: f64,
+    }
+
+    #[derive(candid::CandidType)]
+    struct FirstReleaseApproval {
+        employee_nip: u64,
+        attendance_date: u64,
+        wage_amount: f64,
+        status: ApprovalStatus,
+        manager_wallet: String,
+    }
+
+    #[test]
+    fn first_release_records_still_decode() {
+        let employee = Employee::from_bytes(Cow::Owned(
+            Encode!(&FirstReleaseEmployee {
+                nip: 3,
+                name: "Ana".to_string(),
+                age: 30,
+                pension_age: 56,
+                wage_per_hour: 12.5,
+                sk_file: None,
+                wallet_address: Principal::anonymous().to_text(),
+                created_at: 1,
+                updated_at: None,
+            })
+            .unwrap(),
+        ));
+        assert_eq!(employee.wage_per_hour, 1_250);
+        assert_eq!(employee.years_to_pension, 26);
+        assert_eq!(employee.currency, DEFAULT_CURRENCY);
+        assert!(employee.is_active && !employee.has_sk_file);
+
+        let attendance = Attendance::from_bytes(Cow::Owned(
+            Encode!(&FirstReleaseAttendance {
+                check_in: 0,
+                check_out: 8 * NANOS_PER_HOUR,
+                total_hours: 8.0,
+                daily_wage: 100.0,
+            })
+            .unwrap(),
+        ));
+        assert_eq!((attendance.daily_wage, attendance.net_wage), (10_000, 10_000));
+
+        let approval = PayrollApproval::from_bytes(Cow::Owned(
+            Encode!(&FirstReleaseApproval {
+                employee_nip: 3,
+                attendance_date: 19_723,
+                wage_amount: 100.0,
+                status: ApprovalStatus::Approved,
+                manager_wallet: Principal::anonymous().to_text(),
+            })
+            .unwrap(),
+        ));
+        assert_eq!(approval.wage_amount, 10_000);
+        assert!(approval.status == ApprovalStatus::Approved && approval.payment.is_none());
+    }
+
+    #[test]
+    fn rate_transform_keeps_only_the_rounded_amount() {
+        let response = |body: &str| TransformArgs {
+            response: HttpResponse {
+                status: Nat::from(200u32),
+                headers: Vec::new(),
+                body: body.as_bytes().to_vec(),
+            },
+            context: Vec::new(),
+        };
+        let first = transform_rate_response(response(r#"{"data":{"base":"ICP","amount":"12.3456789012"},"ts":1}"#));
+        let second = transform_rate_response(response(r#"{"data":{"amount":"12.345678904","base":"ICP"},"ts":2}"#));
+        assert_eq!(first.body, br#"{"data":{"amount":"12.34567890"}}"#.to_vec());
+        assert_eq!(first.body, second.body);
+        assert!(transform_rate_response(response("not json")).body.is_empty());
+    }
+
+    // Recompute the root hash of a witness from its CBOR encoding
+    fn witness_root(cbor: &mut &[u8]) -> [u8; 32] {
+        fn take_bytes<'a>(cbor: &mut &'a [u8]) -> &'a [u8] {
+            let (len, skip) = match cbor[0] & 0x1f {
+                len @ 0..=23 => (len as usize, 1),
+                24 => (cbor[1] as usize, 2),
+                25 => (u16::from_be_bytes([cbor[1], cbor[2]]) as usize, 3),
+                other => panic!("unexpected length encoding {}", other),
+            };
+            let (bytes, rest) = cbor[skip..].split_at(len);
+            *cbor = rest;
+            bytes
+        }
+        let node_type = cbor[1];
+        *cbor = &cbor[2..];
+        match node_type {
+            1 => {
+                let left = witness_root(cbor);
+                let right = witness_root(cbor);
+                tree_hash("ic-hashtree-fork", &[&left, &right])
+            }
+            2 => {
+                let label = take_bytes(cbor);
+                let subtree = witness_root(cbor);
+                tree_hash("ic-hashtree-labeled", &[label, &subtree])
+            }
+            3 => tree_hash("ic-hashtree-leaf", &[take_bytes(cbor)]),
+            4 => take_bytes(cbor).try_into().unwrap(),
+            other => panic!("unexpected node type {}", other),
+        }
+    }
+
+    #[test]
+    fn incremental_tree_matches_a_rebuild() {
+        let nips = [0, 1, 2, 5, 6, 1 << 40, u64::MAX - 1, u64::MAX];
+        let mut tree = EmployeeTree {
+            leaves: BTreeMap::new(),
+            forks: BTreeMap::new(),
+        };
+        for nip in nips {
+            tree.update(nip, Some(&nip.to_be_bytes()));
+        }
+        tree.update(5, None);
+        tree.update(1, Some(b"updated"));
+
+        let mut rebuilt = EmployeeTree {
+            leaves: tree.leaves.clone(),
+            forks: BTreeMap::new(),
+        };
+        rebuilt.rehash(64, 0);
+        assert_eq!(tree.root_hash(), rebuilt.root_hash());
+        assert_eq!(tree.forks, rebuilt.forks);
+
+        for nip in nips.into_iter().filter(|nip| *nip != 5) {
+            let bytes = if nip == 1 { b"updated".to_vec() } else { nip.to_be_bytes().to_vec() };
+            let mut witness = Vec::new();
+            tree.write_witness(&mut witness, tree.top(64, 0).unwrap(), nip, &bytes);
+            assert_eq!(witness_root(&mut witness.as_slice()), tree.root_hash());
+        }
+
+        for nip in nips {
+            tree.update(nip, None);
+        }
+        assert!(tree.forks.is_empty());
+        assert_eq!(tree.root_hash(), tree_hash("ic-hashtree-empty", &[]));
+    }
+
+    #[test]
+    fn ages_outside_the_working_range_are_rejected() {
+        let with_age = |age| EmployeePayload { age, ..sample_payload() };
+        assert!(validate_employee_payload(&with_age(15), None).is_err());
+        assert!(validate_employee_payload(&with_age(16), None).is_ok());
+        assert!(validate_employee_payload(&with_age(100), None).is_ok());
+        assert!(validate_employee_payload(&with_age(101), None).is_err());
+    }
+
+    #[test]
+    fn imported_employees_are_validated_and_normalized() {
+        let imported = validate_imported_employee(Employee {
+            has_sk_file: true,
+            years_to_pension: 0,
+            ..sample_employee(4)
+        })
+        .ok()
+        .expect("a valid record is imported");
+        assert!(!imported.has_sk_file);
+        assert_eq!(imported.years_to_pension, DEFAULT_RETIREMENT_AGE - 30);
+
+        assert!(validate_imported_employee(sample_employee(u64::MAX)).is_err());
+        assert!(validate_imported_employee(Employee { age: 12, ..sample_employee(4) }).is_err());
+
+        EMPLOYEE_STORAGE.with(|storage| storage.borrow_mut().insert(1, sample_employee(1)));
+        assert!(validate_imported_employee(sample_employee(4)).is_err());
+    }
+
+    #[test]
+    fn hex_accepts_only_hex_digits() {
+        assert_eq!(decode_hex("00fF"), Some(vec![0x00, 0xff]));
+        assert_eq!(decode_hex("+f"), None);
+        assert_eq!(decode_hex("-1"), None);
+        assert_eq!(decode_hex("abc"), None);
+        assert_eq!(encode_hex(&[0x0a, 0xbc]), "0abc");
+    }
+
+    #[test]
+    fn wages_outside_the_bounds_are_rejected() {
+        WAGE_BOUNDS.with(|cell| {
+            cell.borrow_mut()
+                .set(WageBounds {
+                    min_wage_per_hour: 1_000,
+                    max_wage_per_hour: Some(5_000),
+                })
+                .expect("Cannot set the wage bounds")
+        });
+        assert!(validate_wage_bounds(999).is_err());
+        assert!(validate_wage_bounds(1_000).is_ok());
+        assert!(validate_wage_bounds(5_000).is_ok());
+        assert!(validate_wage_bounds(5_001).is_err());
+    }
+
+    #[test]
+    fn day_keys_convert_to_civil_dates() {
+        assert_eq!(day_key_to_ymd(0), (1970, 1, 1));
+        assert_eq!(day_key_to_ymd(1_095), (1972, 12, 31));
+        assert_eq!(day_key_to_ymd(11_016), (2000, 2, 29));
+        assert_eq!(day_key_to_ymd(11_017), (2000, 3, 1));
+        assert_eq!(day_key_to_ymd(19_723), (2024, 1, 1));
+        assert_eq!(day_key_to_ymd(19_782), (2024, 2, 29));
+    }
+
+    #[test]
+    fn one_wage_rounds_differently_under_each_mode() {
+        // 7.5 regular hours at 1.01 earn 757.5 minor units and 0.1 overtime hours earn 15.15
+        let wage = |mode| {
+            ROUNDING_MODE.with(|cell| cell.borrow_mut().set(mode).expect("Cannot set the rounding mode"));
+            calculate_daily_wage_with_overtime(27_360 * 1_000_000_000, 101, 27_000 * 1_000_000_000, 150)
+        };
+        assert_eq!(wage(RoundingMode::Floor), 757 + 15);
+        assert_eq!(wage(RoundingMode::NearestCent), 758 + 15);
+        assert_eq!(wage(RoundingMode::Ceil), 758 + 16);
+    }
+
+    #[test]
+    fn non_finite_and_oversized_wages_are_rejected() {
+        for wage_per_hour in [f64::INFINITY, f64::NAN, -1.0, MAX_WAGE_PER_HOUR + 1.0] {
+            let payload = EmployeePayload { wage_per_hour, ..sample_payload() };
+            assert!(validate_employee_payload(&payload, None).is_err());
+        }
+        let salaried = |monthly_salary| EmployeePayload {
+            employment_type: Some(EmploymentType::Salaried),
+            monthly_salary: Some(monthly_salary),
+            ..sample_payload()
+        };
+        assert!(validate_employee_payload(&salaried(f64::INFINITY), None).is_err());
+        assert!(validate_employee_payload(&salaried(MAX_MONTHLY_SALARY), None).is_ok());
+    }
+
+    #[test]
+    fn icp_amounts_convert_to_exact_e8s() {
+        assert_eq!(to_e8s(1.23456789), 123_456_789);
+        assert_eq!(to_e8s(0.1), 10_000_000);
+        assert_eq!(to_e8s(0.000000015), 2);
+        assert_eq!(to_e8s(0.000000014), 1);
+        assert_eq!(to_e8s(42.0), 4_200_000_000);
+        assert_eq!(to_e8s(0.0), 0);
+        assert_eq!(to_e8s(-1.0), 0);
+        assert_eq!(to_e8s(f64::NAN), 0);
+        assert_eq!(to_e8s(1e30), u64::MAX);
+    }
+
+    #[test]
+    fn employee_limit_is_enforced() {
+        MAX_EMPLOYEES.with(|cell| cell.borrow_mut().set(2).expect("Cannot set the employee limit"));
+        EMPLOYEE_STORAGE.with(|storage| {
+            storage.borrow_mut().insert(1, sample_employee(1));
+            storage.borrow_mut().insert(2, sample_employee(2));
+        });
+        assert!(ensure_employee_capacity(1).is_err());
+
+        EMPLOYEE_STORAGE.with(|storage| storage.borrow_mut().remove(&2));
+        assert!(ensure_employee_capacity(1).is_ok());
+        assert!(ensure_employee_capacity(2).is_err());
+    }
+
+    #[test]
+    fn shift_crossing_midnight_is_filed_under_its_check_in_day() {
+        let check_in = 19_723 * NANOS_PER_DAY + 22 * NANOS_PER_HOUR;
+        let check_out = check_in + 4 * NANOS_PER_HOUR;
+        let attendance = price_shift(&sample_employee(1), check_in, check_out, 1_250);
+
+        assert_eq!(store_attendance(1, &attendance, true).ok(), Some(19_723));
+        assert!(get_attendance(1, 19_723).is_ok());
+        assert!(get_attendance(1, 19_724).is_err());
+        assert!(store_attendance(1, &attendance, true).is_err());
+    }
+
+    #[test]
+    fn preview_and_record_refuse_the_same_days() {
+        let monday = 19_723;
+        let saturday = monday + 5;
+        let at = |day: u64| day * NANOS_PER_DAY + 9 * NANOS_PER_HOUR;
+        let attendance = price_shift(&sample_employee(1), at(monday), at(monday) + NANOS_PER_HOUR, 1_250);
+        store_attendance(1, &attendance, false).ok().expect("Monday is a workday");
+        LEAVE_STORAGE.with(|storage| {
+            storage.borrow_mut().insert(
+                (1, monday + 1),
+                LeaveRecord {
+                    leave_type: LeaveType::Sick,
+                    daily_wage: 0,
+                    recorded_at: 0,
+                },
+            )
+        });
+
+        assert!(validate_attendance_day(1, monday, true).is_err());
+        assert!(validate_attendance_day(1, monday + 1, true).is_err());
+        assert!(validate_attendance_day(1, saturday, false).is_err());
+        assert!(validate_attendance_day(1, saturday, true).is_ok());
+        assert!(validate_attendance_day(1, monday + 2, false).is_ok());
+    }
+
+    #[test]
+    fn client_request_ids_follow_a_reassigned_nip() {
+        let key = client_request_key("retry-1").ok().expect("id is valid");
+        let request = ProcessedRequest {
+            nip: 1,
+            date: 19_723,
+            processed_at: 0,
+        };
+        PROCESSED_REQUESTS.with(|requests| requests.borrow_mut().insert(key, request));
+        let attendance = price_shift(&sample_employee(2), 19_723 * NANOS_PER_DAY, 19_723 * NANOS_PER_DAY + 1, 1_250);
+        ATTENDANCE_STORAGE.with(|storage| storage.borrow_mut().insert((2, 19_723), attendance));
+
+        rekey_processed_requests(1, 2);
+
+        assert!(previously_recorded_attendance(&key, 1).is_err());
+        assert!(matches!(previously_recorded_attendance(&key, 2), Ok(Some(_))));
+    }
+
+    #[test]
+    fn archived_employees_are_not_listed_near_retirement() {
+        let retiring = |nip, is_active| Employee {
+            age: DEFAULT_RETIREMENT_AGE,
+            is_active,
+            ..sample_employee(nip)
+        };
+        EMPLOYEE_STORAGE.with(|storage| {
+            storage.borrow_mut().insert(1, retiring(1, true));
+            storage.borrow_mut().insert(2, retiring(2, false));
+        });
+
+        let nips: Vec<u64> = get_employees_near_retirement(0).iter().map(|employee| employee.nip).collect();
+        assert_eq!(nips, vec![1]);
+    }
+
+    #[test]
+    fn sk_file_reads_span_stored_chunks() {
+        SK_FILE_STORAGE.with(|storage| {
+            storage.borrow_mut().insert(
+                1,
+                SkFileInfo {
+                    id: 1,
+                    chunk_count: 2,
+                    size: 7,
+                    is_verified: false,
+                    created_at: 1,
+                    updated_at: None,
+                },
+            )
+        });
+        SK_FILE_CHUNKS.with(|storage| {
+            storage.borrow_mut().insert((1, 0), SkChunk { data: b"abcd".to_vec() });
+            storage.borrow_mut().insert((1, 1), SkChunk { data: b"efg".to_vec() });
+        });
+        let read = |offset, len| get_sk_file_chunk(1, offset, len).ok().expect("SK file is stored");
+
+        assert_eq!(read(0, 7), b"abcdefg");
+        assert_eq!(read(2, 4), b"cdef");
+        assert_eq!(read(4, 100), b"efg");
+        assert!(read(7, 10).is_empty());
+        assert!(get_sk_file_chunk(1, 0, MAX_SK_CHUNK_SIZE + 1).is_err());
+    }
+
+    #[test]
+    fn shifts_must_start_on_their_filed_day() {
+        let check_in = 19_723 * NANOS_PER_DAY + 23 * NANOS_PER_HOUR;
+        assert!(ensure_check_in_on(check_in, 19_723).is_ok());
+        assert!(ensure_check_in_on(check_in + NANOS_PER_HOUR, 19_723).is_err());
+        assert!(ensure_check_in_on(check_in, 19_722).is_err());
+    }
+
+    #[test]
+    fn duplicate_wallet_is_rejected() {
+        let wallet = sample_employee(1).wallet_address;
+        EMPLOYEE_STORAGE.with(|storage| storage.borrow_mut().insert(1, sample_employee(1)));
+
+        assert!(ensure_wallet_unused(&wallet, None).is_err());
+        // Surrounding whitespace doesn't make it a different wallet
+        assert!(ensure_wallet_unused(&format!(" {} ", wallet), None).is_err());
+        // The employee keeping their own wallet on update is fine
+        assert!(ensure_wallet_unused(&wallet, Some(1)).is_ok());
+        assert!(ensure_wallet_unused(&Principal::management_canister().to_text(), None).is_ok());
+    }
+
+    #[test]
+    fn paid_approval_cannot_be_paid_again() {
+        let mut approval = PayrollApproval {
+            status: ApprovalStatus::Approved,
+            ..Default::default()
+        };
+        assert!(ensure_payable(&approval).is_ok());
+
+        approval.status = ApprovalStatus::Paid { block_index: 42 };
+        assert!(ensure_payable(&approval).is_err());
+        // Nor can a new request reset it to Pending for another payout
+        assert!(ensure_approval_replaceable(Some(&approval)).is_err());
+    }
+
+    #[test]
+    fn only_pending_approvals_are_replaceable() {
+        let mut approval = PayrollApproval::default();
+        assert!(ensure_approval_replaceable(None).is_ok());
+        assert!(ensure_approval_replaceable(Some(&approval)).is_ok());
+
+        for status in [ApprovalStatus::Approved, ApprovalStatus::Rejected] {
+            approval.status = status;
+            assert!(ensure_approval_replaceable(Some(&approval)).is_err());
+        }
+    }
+
+    #[test]
+    fn definitely_refused_transfers_are_dropped() {
+        let payment = PendingPayment {
+            amount_e8s: 1,
+            created_at_time: 1,
+        };
+        let approval = PayrollApproval {
+            status: ApprovalStatus::Approved,
+            payment: Some(payment),
+            ..Default::default()
+        };
+        APPROVAL_STORAGE.with(|storage| storage.borrow_mut().insert((1, 2), approval));
+        let has_payment = || APPROVAL_STORAGE.with(|storage| storage.borrow().get(&(1, 2)).unwrap().payment.is_some());
+
+        settle_failed_transfer(1, 2, classify_transfer_error(TransferError::TooOld));
+        assert!(has_payment());
+        settle_failed_transfer(1, 2, classify_transfer_error(TransferError::TemporarilyUnavailable));
+        assert!(has_payment());
+
+        settle_failed_transfer(
+            1,
+            2,
+            classify_transfer_error(TransferError::InsufficientFunds { balance: Nat::from(0u32) }),
+        );
+        assert!(!has_payment());
+    }
+
+    #[test]
+    fn payment_memo_is_stable_per_working_day() {
+        assert_eq!(payment_memo(3, 19_000), payment_memo(3, 19_000));
+        assert_ne!(payment_memo(3, 19_000), payment_memo(3, 19_001));
+        assert_ne!(payment_memo(3, 19_000), payment_memo(4, 19_000));
+    }
+}